@@ -30,8 +30,16 @@ export function activate(context: ExtensionContext) {
 
   const clientOptions: LanguageClientOptions = {
     documentSelector: [{ scheme: 'file', language: 'earthfile' }],
+    initializationOptions: {
+      earthlyVersion: workspace.getConfiguration('earthlyls').get<string>('earthlyVersion'),
+    },
     synchronize: {
-      fileEvents: workspace.createFileSystemWatcher('**/Earthfile')
+      configurationSection: 'earthlyls',
+      fileEvents: [
+        workspace.createFileSystemWatcher('**/Earthfile'),
+        workspace.createFileSystemWatcher('**/.earthlyls.toml'),
+        workspace.createFileSystemWatcher('**/pyproject.toml'),
+      ]
     }
   };
 
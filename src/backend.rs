@@ -1,16 +1,21 @@
 use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
 use std::time::Instant;
 
 use clean_path::Clean;
 use dashmap::DashMap;
 use glob_match::glob_match;
 use path_slash::PathExt;
-use tower_lsp::lsp_types::request::{GotoDeclarationParams, GotoDeclarationResponse};
-use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, LanguageServer};
+use tower_lsp::lsp_types::request::{GotoDeclarationParams, GotoDeclarationResponse, Request};
+use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, ClientSocket, LanguageServer, LspService};
 use tree_sitter::Parser;
 
+use crate::commands::target_graph::{TargetGraphParams, TargetGraphRequest};
 use crate::document::Document;
 use crate::error::{self, GlobResultExt, IOResultExt};
+use crate::import::{is_remote, split_git_ref};
+use crate::reference::Reference;
+use crate::settings::{ProjectSettings, Settings};
 use crate::util::request_failed;
 
 // #[derive(Debug)]
@@ -19,6 +24,17 @@ pub struct Backend {
     pub version: String,
     pub docs: DashMap<Url, Document>,
     pub workspaces: DashMap<String, PathBuf>,
+    pub client_capabilities: OnceLock<ClientCapabilities>,
+    pub settings: RwLock<Settings>,
+    /// the settings read from the configuration files of the workspaces, by directory
+    pub project_settings: DashMap<PathBuf, ProjectSettings>,
+}
+
+/// Build the language server, with its custom requests
+pub fn service(version: String) -> (LspService<Backend>, ClientSocket) {
+    LspService::build(|client| Backend::new(client, version))
+        .custom_method(TargetGraphRequest::METHOD, Backend::target_graph)
+        .finish()
 }
 
 impl Backend {
@@ -27,45 +43,134 @@ impl Backend {
         parser
             .set_language(&tree_sitter_earthfile::language())
             .expect("Unable to load the earthfile language");
-        Backend { client, version, docs: Default::default(), workspaces: Default::default() }
+        Backend {
+            client,
+            version,
+            docs: Default::default(),
+            workspaces: Default::default(),
+            client_capabilities: Default::default(),
+            settings: Default::default(),
+            project_settings: Default::default(),
+        }
     }
 
     pub async fn load_workspaces_docs(&self) {
         for item in self.workspaces.iter() {
             let dir = item.value();
             let name = item.key();
+            if let Err(e) = self.load_project_settings(dir) {
+                self.error(format!("can't load {name} workspace configuration: {e}")).await;
+            }
             if let Err(e) = self.load_workspace_docs(dir) {
                 self.error(format!("can't load {name} workspace documents: {e}")).await;
             }
         }
+        let remote_earthfiles = self.settings.read().unwrap().remote_earthfiles.clone();
+        for (prefix, dir) in remote_earthfiles {
+            if let Err(e) = self.load_project_settings(&dir) {
+                self.error(format!("can't load {prefix} remote configuration: {e}")).await;
+            }
+            if let Err(e) = self.load_workspace_docs(&dir) {
+                self.error(format!("can't load {prefix} remote documents: {e}")).await;
+            }
+        }
         if let Err(e) = crate::diagnostic::publish_diagnostics(self).await {
             self.error(format!("can't publish diagnostic: {e}")).await;
         }
     }
 
+    /// Reload all the documents — except the open ones — when the set of documents may have changed. The diagnostics
+    /// published for the documents no longer loaded, or now without diagnostics, are cleared.
+    pub async fn reload_workspaces_docs(&self) {
+        let mut dropped = Vec::new();
+        self.docs.retain(|uri, doc| {
+            if !doc.is_open && !doc.diagnostics.is_empty() {
+                dropped.push(uri.to_owned());
+            }
+            doc.is_open
+        });
+        self.load_workspaces_docs().await;
+        for uri in dropped {
+            if self.docs.get(&uri).is_none_or(|doc| doc.diagnostics.is_empty()) {
+                self.client.publish_diagnostics(uri, vec![], None).await;
+            }
+        }
+    }
+
+    /// Load the Earthfiles found in a directory, and the other files matching the `include` setting, except the ones
+    /// matching the `exclude` setting or the project configuration. The open documents are kept as is.
+    ///
+    /// The project configuration must be loaded first, with `load_project_settings()`.
     pub fn load_workspace_docs(&self, dir: &Path) -> error::Result<()> {
-        let glob_expr = dir.join("**").join("Earthfile").to_string_lossy().to_string();
-        for f in glob::glob(&glob_expr).glob_ctx(&glob_expr)? {
-            let path = f?;
-            self.docs.insert(
-                Url::from_file_path(&path)
-                    .map_err(|_| error::EarthlylsError::PathToUrl { path: path.to_owned() })?,
-                Document::new(&std::fs::read_to_string(&path).path_ctx(path)?),
-            );
+        let include = self.settings.read().unwrap().include.clone();
+        for pattern in ["**/Earthfile"].into_iter().chain(include.iter().map(String::as_str)) {
+            let glob_expr = dir.join(pattern).to_string_lossy().to_string();
+            for f in glob::glob(&glob_expr).glob_ctx(&glob_expr)? {
+                let path = f?;
+                if self.is_excluded(&path) {
+                    continue;
+                }
+                let uri = Url::from_file_path(&path)
+                    .map_err(|_| error::EarthlylsError::PathToUrl { path: path.to_owned() })?;
+                if self.docs.get(&uri).is_some_and(|d| d.is_open) {
+                    continue;
+                }
+                self.docs
+                    .insert(uri, Document::new(&std::fs::read_to_string(&path).path_ctx(path)?));
+            }
         }
         Ok(())
     }
 
+    /// Whether a file is left out of the documents: it is neither an Earthfile nor matched by the `include` setting, or
+    /// it is matched by the `exclude` setting or the one of the project configuration. The patterns are relative to the
+    /// workspace, or the remote checkout, containing the file. A file outside of them is only checked to be an
+    /// Earthfile.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let is_earthfile = path.file_name().is_some_and(|name| name == "Earthfile");
+        let Some(dir) = self.root_dir(path) else {
+            return !is_earthfile;
+        };
+        let settings = self.settings.read().unwrap();
+        let matches = |patterns: &[String]| {
+            let path = path.to_slash_lossy();
+            patterns.iter().any(|pattern| glob_match(&dir.join(pattern).to_slash_lossy(), &path))
+        };
+        let project_exclude = self.project_settings.get(&dir).is_some_and(|p| matches(&p.exclude));
+        !(is_earthfile || matches(&settings.include))
+            || matches(&settings.exclude)
+            || project_exclude
+    }
+
+    /// The workspace, or the remote checkout, containing a file — the innermost one when they are nested
+    fn root_dir(&self, path: &Path) -> Option<PathBuf> {
+        self.root(path).map(|(dir, _)| dir)
+    }
+
+    /// The innermost workspace or remote checkout containing a file, and whether it is a remote checkout. The workspace
+    /// is preferred when a remote checkout is mapped to the same directory.
+    fn root(&self, path: &Path) -> Option<(PathBuf, bool)> {
+        let remote_dirs: Vec<_> =
+            self.settings.read().unwrap().remote_earthfiles.values().cloned().collect();
+        self.workspaces
+            .iter()
+            .map(|w| (w.value().to_owned(), false))
+            .chain(remote_dirs.into_iter().map(|dir| (dir, true)))
+            .filter(|(dir, _)| path.starts_with(dir))
+            .max_by_key(|(dir, remote)| (dir.components().count(), !remote))
+    }
+
     pub fn match_earthfile_ref(&self, origin: &Url, earthfile_ref: &str) -> Result<Vec<Url>> {
-        let path = origin
-            .to_file_path()
-            .map_err(|_| request_failed("can't compute the earthfile path"))?;
-        let path = path
-            .parent()
-            .ok_or_else(|| request_failed("can't compute the current Earthfile parent"))?;
-        let path = path.join(earthfile_ref).join("Earthfile").clean().to_slash_lossy().to_string();
-        Ok(self
-            .docs
+        let Some(dir) = self.earthfile_ref_dir(origin, earthfile_ref)? else {
+            return Ok(Vec::new());
+        };
+        Ok(self.match_earthfile_dir(&dir))
+    }
+
+    /// Find the Earthfiles in a directory, which may be a glob pattern
+    pub fn match_earthfile_dir(&self, dir: &Path) -> Vec<Url> {
+        let path = dir.join("Earthfile").to_slash_lossy().to_string();
+        self.docs
             .iter()
             .map(|i| i.key().clone())
             .flat_map(|uri| {
@@ -75,19 +180,166 @@ impl Backend {
                     None
                 }
             })
-            .collect())
+            .collect()
+    }
+
+    /// Whether an earthfile reference made in the origin Earthfile matches the target Earthfile
+    pub fn is_earthfile_ref_match(
+        &self,
+        origin: &Url,
+        earthfile_ref: &str,
+        target_uri: &Url,
+    ) -> Result<bool> {
+        let Some(dir) = self.earthfile_ref_dir(origin, earthfile_ref)? else {
+            return Ok(false);
+        };
+        let path = dir.join("Earthfile").to_slash_lossy().to_string();
+        let target_path = target_uri
+            .to_file_path()
+            .map_err(|_| request_failed("can't compute the earthfile path"))?
+            .to_string_lossy()
+            .to_string();
+        Ok(glob_match(&path, &target_path))
+    }
+
+    /// The directory of an Earthfile referenced in the origin Earthfile, or `None` for a remote reference without a
+    /// local checkout
+    pub fn earthfile_ref_dir(&self, origin: &Url, earthfile_ref: &str) -> Result<Option<PathBuf>> {
+        if is_remote(earthfile_ref) {
+            return Ok(self.remote_earthfile_dir(earthfile_ref));
+        }
+        let path = origin
+            .to_file_path()
+            .map_err(|_| request_failed("can't compute the earthfile path"))?;
+        let path = path
+            .parent()
+            .ok_or_else(|| request_failed("can't compute the current Earthfile parent"))?;
+        Ok(Some(path.join(earthfile_ref).clean()))
+    }
+
+    /// The local checkout of a remote earthfile reference, found with the longest matching repository prefix. The git
+    /// reference is ignored: the checkout is used whatever its version.
+    pub fn remote_earthfile_dir(&self, earthfile_ref: &str) -> Option<PathBuf> {
+        let (path, _) = split_git_ref(earthfile_ref);
+        let path = path.trim_end_matches('/');
+        self.settings
+            .read()
+            .unwrap()
+            .remote_earthfiles
+            .iter()
+            .filter(|(prefix, _)| path == *prefix || path.starts_with(&format!("{prefix}/")))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, dir)| dir.join(path[prefix.len()..].trim_start_matches('/')).clean())
+    }
+
+    /// Whether a document is in the local checkout of a remote earthfile. Such a document is only used to resolve the
+    /// references to it: it has no diagnostics and no workspace symbols.
+    pub fn is_remote_doc(&self, uri: &Url) -> bool {
+        let Ok(path) = uri.to_file_path() else {
+            return false;
+        };
+        self.root(&path).is_some_and(|(_, remote)| remote)
+    }
+
+    /// Read the project configuration of a directory. An invalid configuration is ignored, so the documents can still
+    /// be loaded without it.
+    pub fn load_project_settings(&self, dir: &Path) -> error::Result<()> {
+        self.project_settings.remove(dir);
+        if let Some(project) = ProjectSettings::load(dir)? {
+            self.project_settings.insert(dir.to_owned(), project);
+        }
+        Ok(())
+    }
+
+    /// The settings applying to a document: the client ones, completed with the ones of the project containing it
+    pub fn doc_settings(&self, uri: &Url) -> Settings {
+        let settings = self.settings.read().unwrap().clone();
+        let Ok(path) = uri.to_file_path() else {
+            return settings;
+        };
+        let project = self
+            .project_settings
+            .iter()
+            .filter(|p| path.starts_with(p.key()))
+            .max_by_key(|p| p.key().components().count());
+        match project {
+            Some(project) => settings.with_project(project.value()),
+            None => settings,
+        }
+    }
+
+    /// Find the Earthfiles targeted by a reference — the origin one when the reference is local
+    pub fn match_reference(&self, origin: &Url, reference: &Reference) -> Result<Vec<Url>> {
+        match &reference.earthfile {
+            Some(earthfile) => self.match_earthfile_ref(origin, earthfile),
+            None => Ok(vec![origin.to_owned()]),
+        }
+    }
+
+    /// The path of a document relative to its workspace, or its full path when it is not in a workspace
+    pub fn workspace_path(&self, uri: &Url) -> String {
+        let Ok(path) = uri.to_file_path() else {
+            return uri.to_string();
+        };
+        self.workspaces
+            .iter()
+            .find_map(|w| path.strip_prefix(w.value()).ok().map(|p| p.to_slash_lossy().to_string()))
+            .unwrap_or_else(|| path.to_slash_lossy().to_string())
+    }
+
+    /// The path of a document in a remote checkout, prefixed with the remote reference of the checkout, like
+    /// `github.com/earthly/lib/Earthfile`
+    pub fn remote_path(&self, uri: &Url) -> Option<String> {
+        let path = uri.to_file_path().ok()?;
+        let (dir, true) = self.root(&path)? else {
+            return None;
+        };
+        let prefix = self
+            .settings
+            .read()
+            .unwrap()
+            .remote_earthfiles
+            .iter()
+            .filter(|(_, d)| **d == dir)
+            .map(|(prefix, _)| prefix.to_owned())
+            .min()?;
+        let path = path.strip_prefix(&dir).ok()?.to_slash_lossy().to_string();
+        Some(format!("{}/{path}", prefix.trim_end_matches('/')))
+    }
+
+    /// The directory of a document relative to its workspace, `.` for the workspace root
+    pub fn workspace_dir(&self, uri: &Url) -> String {
+        let path = self.workspace_path(uri);
+        match path.rsplit_once('/') {
+            Some((dir, _)) => dir.to_string(),
+            None => ".".to_string(),
+        }
+    }
+
+    pub async fn target_graph(&self, params: TargetGraphParams) -> Result<serde_json::Value> {
+        let now = Instant::now();
+        let res = crate::commands::target_graph::target_graph(self, params);
+        self.info(format!("target_graph() run in {:.2?}", now.elapsed())).await;
+        res
     }
 
     pub async fn error(&self, message: impl AsRef<str>) {
-        self.client.log_message(MessageType::ERROR, message.as_ref()).await
+        self.log(MessageType::ERROR, message).await
     }
 
     pub async fn warn(&self, message: impl AsRef<str>) {
-        self.client.log_message(MessageType::WARNING, message.as_ref()).await
+        self.log(MessageType::WARNING, message).await
     }
 
     pub async fn info(&self, message: impl AsRef<str>) {
-        self.client.log_message(MessageType::INFO, message.as_ref()).await
+        self.log(MessageType::INFO, message).await
+    }
+
+    async fn log(&self, typ: MessageType, message: impl AsRef<str>) {
+        let log_level = self.settings.read().unwrap().log_level;
+        if log_level.logs(typ) {
+            self.client.log_message(typ, message.as_ref()).await
+        }
     }
 }
 
@@ -95,6 +347,13 @@ impl Backend {
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
         let now = Instant::now();
+        let _ = self.client_capabilities.set(params.capabilities);
+        if let Some(options) = params.initialization_options {
+            match Settings::from_value(options) {
+                Ok(settings) => *self.settings.write().unwrap() = settings,
+                Err(e) => self.error(format!("invalid initialization options: {e}")).await,
+            }
+        }
         // store the workspaces locations
         if let Some(workspaces) = params.workspace_folders {
             for workspace in workspaces {
@@ -122,6 +381,13 @@ impl LanguageServer for Backend {
         self.info(format!("initialize() run in {:.2?}", now.elapsed())).await;
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                        ..Default::default()
+                    },
+                )),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
                     trigger_characters: Some(
@@ -138,8 +404,14 @@ impl LanguageServer for Backend {
                 definition_provider: Some(OneOf::Left(true)),
                 declaration_provider: Some(DeclarationCapability::Simple(true)),
                 document_symbol_provider: Some(OneOf::Left(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 references_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
                         SemanticTokensOptions {
@@ -154,6 +426,11 @@ impl LanguageServer for Backend {
                         },
                     ),
                 ),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec![" ".to_string(), "-".to_string()]),
+                    retrigger_characters: Some(vec!["=".to_string()]),
+                    work_done_progress_options: Default::default(),
+                }),
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
                     TextDocumentSyncOptions {
                         change: Some(TextDocumentSyncKind::INCREMENTAL),
@@ -232,15 +509,47 @@ impl LanguageServer for Backend {
         };
     }
 
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let now = Instant::now();
+        let current = self.settings.read().unwrap().clone();
+        let settings = match current.merge_value(params.settings) {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.error(format!("invalid configuration: {e}")).await;
+                return;
+            }
+        };
+        let old_settings =
+            std::mem::replace(&mut *self.settings.write().unwrap(), settings.clone());
+        if settings.include != old_settings.include
+            || settings.exclude != old_settings.exclude
+            || settings.remote_earthfiles != old_settings.remote_earthfiles
+        {
+            // the set of documents changes, reload them all
+            self.reload_workspaces_docs().await;
+        } else if let Err(e) = crate::diagnostic::publish_diagnostics(self).await {
+            self.error(format!("can't publish diagnostics: {e}")).await;
+        }
+        self.info(format!("did_change_configuration() run in {:.2?}", now.elapsed())).await;
+    }
+
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
         let now = Instant::now();
+        let mut reload = false;
         for event in params.changes {
+            if ProjectSettings::is_config_file(&event.uri) {
+                reload = true;
+                continue;
+            }
             match event.typ {
                 FileChangeType::CREATED => {
                     let Ok(path) = event.uri.to_file_path() else {
                         self.error(format!("can't convert {} to file path", event.uri)).await;
                         continue;
                     };
+                    if self.is_excluded(&path) {
+                        continue;
+                    }
                     let content = match std::fs::read_to_string(&path) {
                         Ok(content) => content,
                         Err(e) => {
@@ -256,6 +565,9 @@ impl LanguageServer for Backend {
                         self.error(format!("can't convert {} to file path", event.uri)).await;
                         continue;
                     };
+                    if self.is_excluded(&path) {
+                        continue;
+                    }
                     let content = match std::fs::read_to_string(&path) {
                         Ok(content) => content,
                         Err(e) => {
@@ -277,7 +589,10 @@ impl LanguageServer for Backend {
                 _ => self.warn(format!("unsupported file change type: {:?}", event.typ)).await,
             }
         }
-        if let Err(e) = crate::diagnostic::publish_diagnostics(self).await {
+        if reload {
+            // a project configuration changed, and with it maybe the set of documents
+            self.reload_workspaces_docs().await;
+        } else if let Err(e) = crate::diagnostic::publish_diagnostics(self).await {
             self.error(format!("can't publish diagnostics: {e}")).await;
         }
         self.info(format!("did_change_watched_files() run in {:.2?}", now.elapsed())).await;
@@ -319,6 +634,53 @@ impl LanguageServer for Backend {
         res
     }
 
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let now = Instant::now();
+        let res = crate::commands::call_hierarchy::prepare_call_hierarchy(self, params);
+        self.info(format!("prepare_call_hierarchy() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let now = Instant::now();
+        let res = crate::commands::call_hierarchy::incoming_calls(self, params);
+        self.info(format!("incoming_calls() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let now = Instant::now();
+        let res = crate::commands::call_hierarchy::outgoing_calls(self, params);
+        self.info(format!("outgoing_calls() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let now = Instant::now();
+        let res = crate::commands::rename::prepare_rename(self, params);
+        self.info(format!("prepare_rename() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let now = Instant::now();
+        let res = crate::commands::rename::rename(self, params);
+        self.info(format!("rename() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
     async fn document_symbol(
         &self,
         params: DocumentSymbolParams,
@@ -339,6 +701,23 @@ impl LanguageServer for Backend {
         res
     }
 
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let now = Instant::now();
+        let res = crate::commands::formatting::formatting(self, params);
+        self.info(format!("formatting() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let now = Instant::now();
+        let res = crate::commands::formatting::range_formatting(self, params);
+        self.info(format!("range_formatting() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
     async fn semantic_tokens_range(
         &self,
         params: SemanticTokensRangeParams,
@@ -359,6 +738,20 @@ impl LanguageServer for Backend {
         res
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let now = Instant::now();
+        let res = crate::commands::code_action::code_action(self, params);
+        self.info(format!("code_action() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let now = Instant::now();
+        let res = crate::commands::signature_help::signature_help(self, params);
+        self.info(format!("signature_help() run in {:.2?}", now.elapsed())).await;
+        res
+    }
+
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         let now = Instant::now();
         let res = crate::commands::completion::completion(self, params);
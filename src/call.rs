@@ -0,0 +1,189 @@
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+use tree_sitter::{Node, Point};
+
+use crate::{
+    backend::Backend, document::Document, import::resolve_earthfile_ref, reference::target_names,
+    variable::declarations,
+};
+
+/// An ARG that can be passed to a target
+#[derive(Debug, Clone)]
+pub struct TargetArg {
+    pub name: String,
+    pub required: bool,
+    pub default_value: Option<String>,
+    /// the text of the ARG command
+    pub declaration: String,
+}
+
+impl TargetArg {
+    pub fn detail(&self) -> String {
+        if self.required {
+            "required".to_string()
+        } else if let Some(value) = &self.default_value {
+            format!("default: {value}")
+        } else {
+            "optional".to_string()
+        }
+    }
+}
+
+/// The command being typed: its keyword, its arguments, and the word under the cursor — empty after a space
+pub struct TypedCommand {
+    pub keyword: &'static str,
+    pub args: Vec<String>,
+    pub current: String,
+}
+
+pub fn typed_command(doc: &Document, pos: Position) -> Option<TypedCommand> {
+    let prefix = command_prefix(doc, pos);
+    let mut words: Vec<_> = prefix.split_whitespace().collect();
+    if prefix.ends_with(char::is_whitespace) {
+        words.push("");
+    }
+    let (current, words) = words.split_last()?;
+    let keyword = command_keyword(words)?;
+    Some(TypedCommand {
+        keyword,
+        args: words[keyword.split(' ').count()..].iter().map(ToString::to_string).collect(),
+        current: current.to_string(),
+    })
+}
+
+/// The command keyword at the start of the given words
+fn command_keyword(words: &[&str]) -> Option<&'static str> {
+    COMMAND_KEYWORDS
+        .iter()
+        .chain(&["ELSE IF"])
+        .filter(|k| k.split(' ').eq(words.iter().take(k.split(' ').count()).copied()))
+        .max_by_key(|k| k.len())
+        .copied()
+}
+
+/// The text of the command up to the given position, with the continued lines joined
+fn command_prefix(doc: &Document, pos: Position) -> String {
+    let mut line = pos.line as usize;
+    if line >= doc.rope.len_lines() {
+        return String::new();
+    }
+    let mut res: String = doc.rope.line(line).chars().take(pos.character as usize).collect();
+    while line > 0 {
+        line -= 1;
+        let previous = doc.rope.line(line).to_string();
+        let Some(previous) = previous.trim_end_matches(['\r', '\n']).strip_suffix('\\') else {
+            break;
+        };
+        res = format!("{previous} {res}");
+    }
+    res
+}
+
+/// The target reference in the arguments of a command, split in its earthfile and name parts. The build args come
+/// after the target reference, and are in parentheses with it for COPY.
+pub fn called_target(args: &[String]) -> Option<(&str, &str)> {
+    let (earthfile_ref, name) = args
+        .iter()
+        .rev()
+        .filter(|a| !a.starts_with('-'))
+        .find_map(|a| a.trim_start_matches('(').split_once('+'))?;
+    Some((earthfile_ref, name.split('/').next().unwrap_or(name)))
+}
+
+/// The ARGs that can be passed to a target: the ones declared in the target and the global ones of its Earthfile
+pub fn target_args(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    pos: Position,
+    earthfile_ref: &str,
+    name: &str,
+) -> Result<Vec<TargetArg>> {
+    let uris = if earthfile_ref.is_empty() {
+        vec![uri.to_owned()]
+    } else {
+        let point = Point { row: pos.line as usize, column: pos.character as usize };
+        let root = doc.tree.root_node();
+        let node = root.descendant_for_point_range(point, point).unwrap_or(root);
+        backend.match_earthfile_ref(uri, &resolve_earthfile_ref(doc, node, earthfile_ref))?
+    };
+    let mut res: Vec<TargetArg> = Vec::new();
+    let mut add_args = |target_doc: &Document| {
+        let Some(target) = target_names(target_doc)
+            .into_iter()
+            .find(|n| target_doc.node_content(*n) == name)
+            .and_then(|n| n.parent())
+        else {
+            return;
+        };
+        for arg in declared_args(target_doc, target) {
+            if !res.iter().any(|a| a.name == arg.name) {
+                res.push(arg);
+            }
+        }
+    };
+    for target_uri in uris {
+        if &target_uri == uri {
+            add_args(doc);
+        } else if let Some(target_doc) = backend.docs.get(&target_uri) {
+            add_args(&target_doc);
+        }
+    }
+    Ok(res)
+}
+
+/// The ARGs declared in a target, and the global ARGs of its Earthfile
+pub fn declared_args(doc: &Document, target: Node) -> Vec<TargetArg> {
+    let mut res: Vec<TargetArg> = Vec::new();
+    for d in declarations(doc) {
+        if d.command.kind() != "arg_command"
+            || !(d.target() == Some(target) || d.is_global())
+            || res.iter().any(|a| a.name == d.name)
+        {
+            continue;
+        }
+        res.push(TargetArg {
+            required: d.is_required(),
+            default_value: d.value().map(|v| doc.node_content(v)),
+            declaration: doc.node_content(d.command).trim().to_string(),
+            name: d.name,
+        });
+    }
+    res
+}
+
+/// The keywords of the Earthfile commands
+pub const COMMAND_KEYWORDS: [&str; 33] = [
+    "ARG",
+    "BUILD",
+    "CACHE",
+    "CMD",
+    "COPY",
+    "DO",
+    "ENTRYPOINT",
+    "ENV",
+    "EXPOSE",
+    "FOR",
+    "FROM",
+    "FROM DOCKERFILE",
+    "FUNCTION",
+    "GIT CLONE",
+    "HEALTHCHECK",
+    "HOST",
+    "IF",
+    "IMPORT",
+    "LABEL",
+    "LET",
+    "LOCALLY",
+    "PROJECT",
+    "RUN",
+    "SAVE ARTIFACT",
+    "SAVE IMAGE",
+    "SET",
+    "TRY",
+    "USER",
+    "VERSION",
+    "VOLUME",
+    "WAIT",
+    "WITH DOCKER",
+    "WORKDIR",
+];
@@ -1,8 +1,105 @@
-use clap::Parser;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use tower_lsp::LspService;
+
+use crate::{
+    backend::{self, Backend},
+    error::{self, IOResultExt},
+    graph::GraphFormat,
+};
+
+pub mod check;
+pub mod fmt;
+pub mod graph;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-pub struct Cli {}
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Report the diagnostics of all the Earthfiles in a directory
+    Check {
+        /// The output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// The directory to search the Earthfiles in
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+
+    /// Format the Earthfiles
+    Fmt {
+        /// Don't write the files, just exit with a non-zero status if some of them are not formatted
+        #[arg(long)]
+        check: bool,
+
+        /// The number of spaces of an indentation level
+        #[arg(long, default_value_t = 4)]
+        tab_size: u32,
+
+        /// Indent with tabs instead of spaces
+        #[arg(long)]
+        tabs: bool,
+
+        /// The Earthfiles to format, or the directories to search them in
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Print the dependency graph of the targets of all the Earthfiles in a directory
+    Graph {
+        /// The output format
+        #[arg(short, long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+
+        /// The directory to search the Earthfiles in
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+/// Load the Earthfiles of a directory like in a workspace rooted there. Returns the service holding the backend, and
+/// the absolute path of the directory.
+pub fn load_backend(dir: &Path) -> error::Result<(LspService<Backend>, PathBuf)> {
+    // the backend needs a client, even if we never use it
+    let (service, _) = backend::service(env!("CARGO_PKG_VERSION").to_string());
+    let backend = service.inner();
+    // the document urls must be absolute
+    let root = dir.canonicalize().path_ctx(dir)?;
+    if let Err(e) = backend.load_project_settings(&root) {
+        eprintln!("{e}");
+    }
+    // the workspace must be known before loading the documents, to apply the project exclude patterns
+    backend.workspaces.insert("default".to_string(), root.clone());
+    backend.load_workspace_docs(&root)?;
+    Ok((service, root))
+}
+
+/// Write the output of a command on the standard output. A closed standard output, like when the output is piped to
+/// `head`, is not an error.
+pub fn print(output: &str) -> error::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        res => res.map_err(|source| error::EarthlylsError::Output { source }),
+    }
+}
 
 #[test]
 fn verify_cli() {
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::json;
+use tower_lsp::lsp_types::*;
+
+use crate::{
+    cli::{load_backend, print, OutputFormat},
+    diagnostic::{dependency_cycle::DependencyCycles, doc_diagnostics},
+    error,
+    reference::ResolvedReferences,
+};
+
+/// The diagnostics of an Earthfile, with its path as given on the command line
+#[derive(Debug, Serialize)]
+pub struct FileDiagnostics {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Print the diagnostics of all the Earthfiles in a directory. Returns `false` if some of them are errors.
+pub fn check(dir: &Path, format: OutputFormat) -> error::Result<bool> {
+    let files = diagnostics(dir)?;
+    print(&render(&files, format))?;
+    Ok(!files
+        .iter()
+        .flat_map(|f| &f.diagnostics)
+        .any(|d| d.severity == Some(DiagnosticSeverity::ERROR)))
+}
+
+/// Load the Earthfiles like in a workspace, and compute their diagnostics
+pub fn diagnostics(dir: &Path) -> error::Result<Vec<FileDiagnostics>> {
+    let (service, root) = load_backend(dir)?;
+    let backend = service.inner();
+    let resolved = ResolvedReferences::new(backend)
+        .map_err(|e| error::EarthlylsError::Graph { message: e.message.to_string() })?;
+    let cycles = DependencyCycles::new(backend, &resolved);
+    let mut res = Vec::new();
+    for item in backend.docs.iter() {
+        let Ok(path) = item.key().to_file_path() else {
+            continue;
+        };
+        let path = path.strip_prefix(&root).map(|p| dir.join(p)).unwrap_or(path);
+        let diagnostics = doc_diagnostics(backend, &resolved, &cycles, item.key(), item.value())
+            .map_err(|e| error::EarthlylsError::Diagnostic {
+                path: path.clone(),
+                message: e.message.to_string(),
+            })?;
+        res.push(FileDiagnostics { path, diagnostics });
+    }
+    res.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(res)
+}
+
+/// Render the diagnostics in the given format
+pub fn render(files: &[FileDiagnostics], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => text(files),
+        OutputFormat::Json => serde_json::to_string_pretty(files).unwrap() + "\n",
+        OutputFormat::Sarif => serde_json::to_string_pretty(&sarif(files)).unwrap() + "\n",
+    }
+}
+
+fn text(files: &[FileDiagnostics]) -> String {
+    let mut res = String::new();
+    for f in files {
+        for d in &f.diagnostics {
+            res.push_str(&format!(
+                "{}:{}:{}: {}: {}{}\n",
+                f.path.display(),
+                d.range.start.line + 1,
+                d.range.start.character + 1,
+                severity(d),
+                d.message,
+                code(d).map(|c| format!(" [{c}]")).unwrap_or_default(),
+            ));
+        }
+    }
+    res
+}
+
+fn sarif(files: &[FileDiagnostics]) -> serde_json::Value {
+    let mut rules: Vec<_> = files.iter().flat_map(|f| &f.diagnostics).filter_map(code).collect();
+    rules.sort();
+    rules.dedup();
+    let results: Vec<_> = files
+        .iter()
+        .flat_map(|f| {
+            f.diagnostics.iter().map(|d| {
+                json!({
+                    "ruleId": code(d),
+                    "level": match severity(d) {
+                        level @ ("error" | "warning") => level,
+                        _ => "note",
+                    },
+                    "message": { "text": d.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": f.path.to_string_lossy().replace('\\', "/") },
+                            "region": {
+                                "startLine": d.range.start.line + 1,
+                                "startColumn": d.range.start.character + 1,
+                                "endLine": d.range.end.line + 1,
+                                "endColumn": d.range.end.character + 1,
+                            },
+                        },
+                    }],
+                })
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "earthlyls",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/glehmann/earthlyls",
+                    "rules": rules.iter().map(|r| json!({ "id": r })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+fn severity(d: &Diagnostic) -> &'static str {
+    match d.severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        _ => "hint",
+    }
+}
+
+fn code(d: &Diagnostic) -> Option<&str> {
+    match &d.code {
+        Some(NumberOrString::String(code)) => Some(code),
+        _ => None,
+    }
+}
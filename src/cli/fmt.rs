@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    document::Document,
+    error::{self, GlobResultExt, IOResultExt},
+    format::format,
+};
+
+/// Format the Earthfiles found in the given paths. Returns `false` if some of the files can't be formatted, or, when
+/// checking, are not already formatted. Each level is indented with the given string.
+pub fn fmt(check: bool, indent: &str, paths: &[PathBuf]) -> error::Result<bool> {
+    let mut ok = true;
+    for path in earthfiles(paths)? {
+        let text = std::fs::read_to_string(&path).path_ctx(&path)?;
+        let Some(formatted) = format(&Document::new(&text), indent) else {
+            eprintln!("{}: can't format a file with syntax errors", path.display());
+            ok = false;
+            continue;
+        };
+        if formatted == text {
+            continue;
+        }
+        if check {
+            println!("{}: not formatted", path.display());
+            ok = false;
+        } else {
+            std::fs::write(&path, formatted).path_ctx(&path)?;
+            println!("{}: formatted", path.display());
+        }
+    }
+    Ok(ok)
+}
+
+/// The Earthfiles given directly, and the ones found in the given directories
+pub fn earthfiles(paths: &[PathBuf]) -> error::Result<Vec<PathBuf>> {
+    let mut res = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            res.extend(find_earthfiles(path)?);
+        } else {
+            res.push(path.to_owned());
+        }
+    }
+    Ok(res)
+}
+
+fn find_earthfiles(dir: &Path) -> error::Result<Vec<PathBuf>> {
+    let glob_expr = dir.join("**").join("Earthfile").to_string_lossy().to_string();
+    let mut res = Vec::new();
+    for f in glob::glob(&glob_expr).glob_ctx(&glob_expr)? {
+        res.push(f?);
+    }
+    Ok(res)
+}
@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use crate::{
+    cli::{load_backend, print},
+    error,
+    graph::{self, Graph, GraphFormat},
+};
+
+/// Print the target graph of all the Earthfiles in a directory
+pub fn graph(dir: &Path, format: GraphFormat) -> error::Result<bool> {
+    let mut output = target_graph(dir)?.render(format);
+    if format == GraphFormat::Json {
+        output.push('\n');
+    }
+    print(&output)?;
+    Ok(true)
+}
+
+/// Load the Earthfiles like in a workspace rooted in the given directory, and compute their target graph
+pub fn target_graph(dir: &Path) -> error::Result<Graph> {
+    let (service, _) = load_backend(dir)?;
+    let backend = service.inner();
+    graph::target_graph(backend)
+        .map_err(|e| error::EarthlylsError::Graph { message: e.message.to_string() })
+}
@@ -1,8 +1,14 @@
+pub mod call_hierarchy;
+pub mod code_action;
 pub mod completion;
 pub mod document_symbol;
+pub mod formatting;
 pub mod goto_definition;
 pub mod hover;
 pub mod references;
+pub mod rename;
 pub mod semantic_tokens;
 pub mod semantic_tokens_full;
+pub mod signature_help;
 pub mod symbol;
+pub mod target_graph;
@@ -0,0 +1,144 @@
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+use tree_sitter::Node;
+
+use super::references::{target_at, target_locations};
+use crate::{
+    backend::Backend,
+    document::Document,
+    import::enclosing_target,
+    reference::{is_function_target, references, target_name_at, target_names},
+    util::{request_failed, ToLSPRange, ToTSRange},
+};
+
+pub fn prepare_call_hierarchy(
+    backend: &Backend,
+    params: CallHierarchyPrepareParams,
+) -> Result<Option<Vec<CallHierarchyItem>>> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let pos = params.text_document_position_params.position;
+    let Some((target_uri, target_name)) = target_at(backend, uri, pos)? else {
+        return Ok(None);
+    };
+    let Some(target_doc) = backend.docs.get(&target_uri) else {
+        return Ok(None);
+    };
+    let items: Vec<_> = target_names(&target_doc)
+        .into_iter()
+        .filter(|n| target_doc.node_content(*n) == target_name)
+        .filter_map(|n| target_item(backend, &target_uri, &target_doc, n.parent()?))
+        .collect();
+    Ok(if items.is_empty() { None } else { Some(items) })
+}
+
+/// The targets referencing the given target
+pub fn incoming_calls(
+    backend: &Backend,
+    params: CallHierarchyIncomingCallsParams,
+) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+    let item = &params.item;
+    let mut res: Vec<CallHierarchyIncomingCall> = Vec::new();
+    for location in target_locations(backend, &item.uri, &item.name)? {
+        if location.is_declaration {
+            continue;
+        }
+        let Some(doc) = backend.docs.get(&location.uri) else {
+            continue;
+        };
+        // the references in the base target are not in a target, and can't be part of the hierarchy
+        let pos = location.range.to_ts_range().start;
+        let Some(caller) = doc
+            .tree
+            .root_node()
+            .descendant_for_point_range(pos, pos)
+            .and_then(enclosing_target)
+            .and_then(|t| target_item(backend, &location.uri, &doc, t))
+        else {
+            continue;
+        };
+        match res.iter_mut().find(|c| c.from.uri == caller.uri && c.from.range == caller.range) {
+            Some(call) => call.from_ranges.push(location.range),
+            None => res.push(CallHierarchyIncomingCall {
+                from: caller,
+                from_ranges: vec![location.range],
+            }),
+        }
+    }
+    Ok(Some(res))
+}
+
+/// The targets and functions referenced in the BUILD, FROM, COPY and DO commands of the given target
+pub fn outgoing_calls(
+    backend: &Backend,
+    params: CallHierarchyOutgoingCallsParams,
+) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+    let item = &params.item;
+    let uri = &item.uri;
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let Some(target) =
+        target_name_at(doc, item.selection_range.to_ts_range().start).and_then(|n| n.parent())
+    else {
+        return Ok(None);
+    };
+    let mut res: Vec<CallHierarchyOutgoingCall> = Vec::new();
+    for reference in references(doc) {
+        if enclosing_target(reference.node) != Some(target) {
+            continue;
+        }
+        for callee_uri in backend.match_reference(uri, &reference)? {
+            let callees = if &callee_uri == uri {
+                callee_items(backend, &callee_uri, doc, &reference.name)
+            } else if let Some(callee_doc) = backend.docs.get(&callee_uri) {
+                callee_items(backend, &callee_uri, &callee_doc, &reference.name)
+            } else {
+                Vec::new()
+            };
+            for callee in callees {
+                let range = reference.range.to_lsp_range();
+                match res.iter_mut().find(|c| c.to.uri == callee.uri && c.to.range == callee.range)
+                {
+                    Some(call) => call.from_ranges.push(range),
+                    None => {
+                        res.push(CallHierarchyOutgoingCall { to: callee, from_ranges: vec![range] })
+                    }
+                }
+            }
+        }
+    }
+    Ok(Some(res))
+}
+
+fn callee_items(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    name: &str,
+) -> Vec<CallHierarchyItem> {
+    target_names(doc)
+        .into_iter()
+        .filter(|n| doc.node_content(*n) == name)
+        .filter_map(|n| target_item(backend, uri, doc, n.parent()?))
+        .collect()
+}
+
+fn target_item(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    target: Node,
+) -> Option<CallHierarchyItem> {
+    let name = target.child_by_field_name("name")?;
+    Some(CallHierarchyItem {
+        name: doc.node_content(name),
+        kind: SymbolKind::FUNCTION,
+        tags: None,
+        detail: Some(format!(
+            "{} in {}",
+            if is_function_target(target) { "function" } else { "target" },
+            backend.workspace_path(uri)
+        )),
+        uri: uri.to_owned(),
+        range: target.range().to_lsp_range(),
+        selection_range: name.range().to_lsp_range(),
+        data: None,
+    })
+}
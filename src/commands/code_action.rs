@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+
+use crate::{
+    backend::Backend,
+    diagnostic::{QuickFix, SOURCE},
+    util::request_failed,
+};
+
+/// Build the quick fixes from the data attached to the diagnostics sent back by the client
+pub fn code_action(
+    backend: &Backend,
+    params: CodeActionParams,
+) -> Result<Option<CodeActionResponse>> {
+    let uri = &params.text_document.uri;
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let res = params
+        .context
+        .diagnostics
+        .iter()
+        .filter(|d| d.source.as_deref() == Some(SOURCE))
+        .filter_map(|d| {
+            // not all the clients send the data back, so fallback on the diagnostics we have published
+            let fix = QuickFix::from_diagnostic(d).or_else(|| {
+                doc.diagnostics
+                    .iter()
+                    .find(|pd| pd.range == d.range && pd.message == d.message)
+                    .and_then(QuickFix::from_diagnostic)
+            })?;
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: fix.title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![d.to_owned()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.to_owned(), fix.edits)])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            }))
+        })
+        .collect();
+    Ok(Some(res))
+}
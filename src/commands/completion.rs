@@ -1,9 +1,22 @@
+use std::collections::{BTreeSet, HashSet};
+
+use clean_path::Clean;
+use path_slash::PathExt;
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
 use tree_sitter::Point;
 
-use crate::{backend::Backend, util::request_failed};
+use crate::{
+    backend::Backend,
+    call::{called_target, target_args, typed_command, TypedCommand, COMMAND_KEYWORDS},
+    descriptions::command_options,
+    document::Document,
+    import::resolve_earthfile_ref,
+    reference::{is_function_target, target_names},
+    util::request_failed,
+    variable::{visible_declarations, BUILTIN_ARGS},
+};
 
-pub const TRIGGER_CHARACTERS: [&str; 4] = ["=", "$", "{", "-"];
+pub const TRIGGER_CHARACTERS: [&str; 5] = ["=", "$", "{", "-", "+"];
 
 pub fn completion(
     backend: &Backend,
@@ -11,8 +24,23 @@ pub fn completion(
 ) -> Result<Option<CompletionResponse>> {
     let pos = &params.text_document_position.position;
     let uri = &params.text_document_position.text_document.uri;
-    let tree = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document"))?.tree;
-    let root_node = tree.root_node();
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document"))?;
+    if let Some(res) = build_arg_completion(backend, uri, doc, *pos)? {
+        return Ok(Some(res));
+    }
+    if let Some(res) = option_completion(doc, *pos) {
+        return Ok(Some(res));
+    }
+    if let Some(res) = variable_completion(doc, *pos) {
+        return Ok(Some(res));
+    }
+    if let Some(res) = target_completion(backend, uri, doc, *pos)? {
+        return Ok(Some(res));
+    }
+    if let Some(res) = path_completion(backend, uri, doc, *pos)? {
+        return Ok(Some(res));
+    }
+    let root_node = doc.tree.root_node();
     let pos = Point {
         row: pos.line as usize,
         column: if pos.character == 0 { 1 } else { pos.character } as usize,
@@ -49,38 +77,286 @@ pub fn completion(
     }
 }
 
-const COMMAND_KEYWORDS: [&str; 33] = [
-    "ARG",
-    "BUILD",
-    "CACHE",
-    "CMD",
-    "COPY",
-    "DO",
-    "ENTRYPOINT",
-    "ENV",
-    "EXPOSE",
-    "FOR",
-    "FROM",
-    "FROM DOCKERFILE",
-    "FUNCTION",
-    "GIT CLONE",
-    "HEALTHCHECK",
-    "HOST",
-    "IF",
-    "IMPORT",
-    "LABEL",
-    "LET",
-    "LOCALLY",
-    "PROJECT",
-    "RUN",
-    "SAVE ARTIFACT",
-    "SAVE IMAGE",
-    "SET",
-    "TRY",
-    "USER",
-    "VERSION",
-    "VOLUME",
-    "WAIT",
-    "WITH DOCKER",
-    "WORKDIR",
-];
+/// Complete the options of the command when the word being typed starts with `-`
+fn option_completion(doc: &Document, pos: Position) -> Option<CompletionResponse> {
+    let TypedCommand { keyword, args, current } = typed_command(doc, pos)?;
+    if !current.starts_with('-') || current.contains('=') {
+        return None;
+    }
+    let kind = match keyword {
+        "ELSE IF" => "if_command".to_string(),
+        _ => format!("{}_command", keyword.to_lowercase().replace(' ', "_")),
+    };
+    let options = command_options(&kind);
+    // the options must come before the other arguments
+    let mut expect_value = false;
+    for word in &args {
+        if expect_value {
+            expect_value = false;
+            continue;
+        }
+        if !word.starts_with('-') {
+            return None;
+        }
+        expect_value = !word.contains('=')
+            && options.iter().any(|o| o.name == *word && o.synopsis.contains(' '));
+    }
+    let range = Range {
+        start: Position {
+            line: pos.line,
+            character: pos.character - current.chars().count() as u32,
+        },
+        end: pos,
+    };
+    Some(CompletionResponse::Array(
+        options
+            .into_iter()
+            .map(|o| CompletionItem {
+                label: o.name.to_string(),
+                kind: Some(CompletionItemKind::PROPERTY),
+                detail: Some(o.synopsis.to_string()),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: o.description.to_string(),
+                })),
+                tags: o.deprecated.then(|| vec![CompletionItemTag::DEPRECATED]),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: o.name.to_string(),
+                })),
+                ..Default::default()
+            })
+            .collect(),
+    ))
+}
+
+/// Complete the ARGs of the target referenced by a BUILD, FROM, COPY or DO command as `--NAME=` build args
+fn build_arg_completion(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    pos: Position,
+) -> Result<Option<CompletionResponse>> {
+    let Some(TypedCommand { keyword, args, current }) = typed_command(doc, pos) else {
+        return Ok(None);
+    };
+    if !["BUILD", "COPY", "DO", "FROM"].contains(&keyword)
+        || !current.starts_with('-')
+        || current.contains('=')
+    {
+        return Ok(None);
+    }
+    let Some((earthfile_ref, name)) = called_target(&args) else {
+        return Ok(None);
+    };
+    let range = Range {
+        start: Position {
+            line: pos.line,
+            character: pos.character - current.chars().count() as u32,
+        },
+        end: pos,
+    };
+    let items = target_args(backend, uri, doc, pos, earthfile_ref, name)?
+        .into_iter()
+        .filter(|a| !args.iter().any(|given| given.starts_with(&format!("--{}=", a.name))))
+        .map(|a| CompletionItem {
+            label: format!("--{}", a.name),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(a.detail()),
+            documentation: Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```earthfile\n{}\n```", a.declaration),
+            })),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range,
+                new_text: format!("--{}=", a.name),
+            })),
+            ..Default::default()
+        })
+        .collect();
+    Ok(Some(CompletionResponse::Array(items)))
+}
+
+/// Complete the variable names after `$` or `${`, in the Earthfile commands as well as in the shell fragments
+fn variable_completion(doc: &Document, pos: Position) -> Option<CompletionResponse> {
+    let line = doc.rope.get_line(pos.line as usize)?;
+    let prefix: String = line.chars().take(pos.character as usize).collect();
+    let head = prefix.trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    if !head.ends_with('$') && !head.ends_with("${") {
+        return None;
+    }
+    let typed = &prefix[head.len()..];
+    let range = Range {
+        start: Position { line: pos.line, character: pos.character - typed.chars().count() as u32 },
+        end: pos,
+    };
+    // nothing is expanded in a comment
+    let trigger = Point { row: pos.line as usize, column: head.len() - 1 };
+    let node = doc.tree.root_node().descendant_for_point_range(trigger, trigger)?;
+    if node.kind() == "comment" {
+        return None;
+    }
+    let point = Point { row: pos.line as usize, column: pos.character as usize };
+    let declarations = visible_declarations(doc, point);
+    let mut items: Vec<_> = declarations
+        .iter()
+        .map(|d| {
+            let location = match d.target().and_then(|t| t.child_by_field_name("name")) {
+                Some(name) => format!("target `{}`", doc.node_content(name)),
+                None => "the base target".to_string(),
+            };
+            let mut documentation = format!(
+                "Declared with `{}` in {location}, line {}",
+                d.keyword(),
+                d.command.start_position().row + 1
+            );
+            if let Some(value) = d.value() {
+                let kind = if d.keyword() == "ARG" { "Default value" } else { "Value" };
+                documentation.push_str(&format!("\n\n{kind}: `{}`", doc.node_content(value)));
+            }
+            CompletionItem {
+                label: d.name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: doc.node_content(d.command).lines().next().map(|l| l.trim().to_string()),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: documentation,
+                })),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: d.name.clone(),
+                })),
+                ..Default::default()
+            }
+        })
+        .collect();
+    items.extend(BUILTIN_ARGS.iter().filter(|a| !declarations.iter().any(|d| d.name == **a)).map(
+        |a| CompletionItem {
+            label: a.to_string(),
+            kind: Some(CompletionItemKind::CONSTANT),
+            detail: Some("builtin arg".to_string()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit { range, new_text: a.to_string() })),
+            ..Default::default()
+        },
+    ));
+    Some(CompletionResponse::Array(items))
+}
+
+/// Complete the target names after a `+`, or the function names after `DO +`
+fn target_completion(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    pos: Position,
+) -> Result<Option<CompletionResponse>> {
+    let Some(TypedCommand { keyword, current, .. }) = typed_command(doc, pos) else {
+        return Ok(None);
+    };
+    if !["BUILD", "COPY", "DO", "FROM"].contains(&keyword) || current.starts_with('-') {
+        return Ok(None);
+    }
+    let Some((earthfile_ref, name)) = current.split_once('+') else {
+        return Ok(None);
+    };
+    if name.contains('/') {
+        return Ok(None);
+    }
+    let uris = if earthfile_ref.is_empty() {
+        vec![uri.to_owned()]
+    } else {
+        let point = Point { row: pos.line as usize, column: pos.character as usize };
+        let root = doc.tree.root_node();
+        let node = root.descendant_for_point_range(point, point).unwrap_or(root);
+        backend.match_earthfile_ref(uri, &resolve_earthfile_ref(doc, node, earthfile_ref))?
+    };
+    let range = Range {
+        start: Position { line: pos.line, character: pos.character - name.chars().count() as u32 },
+        end: pos,
+    };
+    let functions = keyword == "DO";
+    let mut names = HashSet::new();
+    let mut add_names = |target_doc: &Document| {
+        for node in target_names(target_doc) {
+            if node.parent().is_some_and(|t| is_function_target(t) == functions) {
+                names.insert(target_doc.node_content(node));
+            }
+        }
+    };
+    for target_uri in uris {
+        if &target_uri == uri {
+            add_names(doc);
+        } else if let Some(target_doc) = backend.docs.get(&target_uri) {
+            add_names(&target_doc);
+        }
+    }
+    let mut names: Vec<_> = names.into_iter().collect();
+    names.sort();
+    Ok(Some(CompletionResponse::Array(
+        names
+            .into_iter()
+            .map(|name| CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(if functions { "function" } else { "target" }.to_string()),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit { range, new_text: name })),
+                ..Default::default()
+            })
+            .collect(),
+    )))
+}
+
+/// Complete the earthfile part of a reference with the paths of the directories containing a known Earthfile
+fn path_completion(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    pos: Position,
+) -> Result<Option<CompletionResponse>> {
+    let Some(TypedCommand { keyword, current, .. }) = typed_command(doc, pos) else {
+        return Ok(None);
+    };
+    if !["BUILD", "COPY", "DO", "FROM", "IMPORT"].contains(&keyword)
+        || current.contains('+')
+        || !(current.starts_with("./") || current.starts_with("../"))
+    {
+        return Ok(None);
+    }
+    let Some((dir, name)) = current.rsplit_once('/') else {
+        return Ok(None);
+    };
+    let path =
+        uri.to_file_path().map_err(|_| request_failed("can't compute the earthfile path"))?;
+    let base = path
+        .parent()
+        .ok_or_else(|| request_failed("can't compute the current Earthfile parent"))?
+        .join(dir)
+        .clean();
+    let mut paths = BTreeSet::new();
+    for item in backend.docs.iter() {
+        let Ok(path) = item.key().to_file_path() else {
+            continue;
+        };
+        let Some(relative) = path.parent().and_then(|p| p.strip_prefix(&base).ok()) else {
+            continue;
+        };
+        let relative = relative.to_slash_lossy().to_string();
+        if !relative.is_empty() && relative.starts_with(name) {
+            paths.insert(relative);
+        }
+    }
+    let range = Range {
+        start: Position { line: pos.line, character: pos.character - name.chars().count() as u32 },
+        end: pos,
+    };
+    Ok(Some(CompletionResponse::Array(
+        paths
+            .into_iter()
+            .map(|path| CompletionItem {
+                label: path.clone(),
+                kind: Some(CompletionItemKind::FOLDER),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit { range, new_text: path })),
+                ..Default::default()
+            })
+            .collect(),
+    )))
+}
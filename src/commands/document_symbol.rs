@@ -7,6 +7,8 @@ use tree_sitter::{Node, Query, QueryCursor};
 use crate::{
     backend::Backend,
     document::Document,
+    import,
+    reference::is_function_target,
     util::{request_failed, RopeProvider, ToLSPRange},
 };
 
@@ -16,8 +18,103 @@ pub fn document_symbol(
 ) -> Result<Option<DocumentSymbolResponse>> {
     let uri = &params.text_document.uri;
     let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
-    let res = symbols(uri, doc);
-    Ok(Some(DocumentSymbolResponse::Flat(res)))
+    let hierarchical = backend
+        .client_capabilities
+        .get()
+        .and_then(|c| {
+            c.text_document.as_ref()?.document_symbol.as_ref()?.hierarchical_document_symbol_support
+        })
+        .unwrap_or(false);
+    if hierarchical {
+        Ok(Some(DocumentSymbolResponse::Nested(nested_symbols(doc))))
+    } else {
+        Ok(Some(DocumentSymbolResponse::Flat(symbols(uri, doc))))
+    }
+}
+
+/// The targets and functions, with the symbols of their commands as children. The symbols of the base target
+/// commands are at the top level, along with the targets.
+pub fn nested_symbols(doc: &Document) -> Vec<DocumentSymbol> {
+    let root = doc.tree.root_node();
+    let mut res = Vec::new();
+    let mut cursor = root.walk();
+    for child in root.named_children(&mut cursor) {
+        if child.kind() == "target" {
+            res.extend(target_symbol(doc, child));
+        } else {
+            command_symbols(doc, child, &mut res);
+        }
+    }
+    res
+}
+
+fn target_symbol(doc: &Document, target: Node) -> Option<DocumentSymbol> {
+    let name = target.child_by_field_name("name")?;
+    let mut children = Vec::new();
+    command_symbols(doc, target, &mut children);
+    Some(DocumentSymbol {
+        name: doc.node_content(name),
+        detail: Some(if is_function_target(target) { "function" } else { "target" }.to_string()),
+        kind: SymbolKind::FUNCTION,
+        tags: None,
+        deprecated: None,
+        range: target.range().to_lsp_range(),
+        selection_range: name.range().to_lsp_range(),
+        children: Some(children),
+    })
+}
+
+/// The symbols of the commands in a node, including the ones in nested blocks
+fn command_symbols(doc: &Document, node: Node, res: &mut Vec<DocumentSymbol>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let (name_node, kind) = match child.kind() {
+            "arg_command" | "let_command" | "set_command" => {
+                (child.child_by_field_name("name"), SymbolKind::VARIABLE)
+            }
+            "env_command" => (child.child_by_field_name("key"), SymbolKind::KEY),
+            "import_command" => {
+                res.extend(import::imports(doc).into_iter().find(|i| i.node == child).map(|i| {
+                    DocumentSymbol {
+                        name: i.alias,
+                        detail: Some(i.earthfile),
+                        kind: SymbolKind::MODULE,
+                        tags: None,
+                        deprecated: None,
+                        range: child.range().to_lsp_range(),
+                        selection_range: i
+                            .alias_node
+                            .unwrap_or(i.earthfile_node)
+                            .range()
+                            .to_lsp_range(),
+                        children: None,
+                    }
+                }));
+                continue;
+            }
+            "save_image_command" => (child.child_by_field_name("images"), SymbolKind::OBJECT),
+            "save_artifact_command" => (child.child_by_field_name("src"), SymbolKind::FILE),
+            kind if kind == "block" || kind.ends_with("_command") || kind.ends_with("_block") => {
+                command_symbols(doc, child, res);
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(name_node) = name_node else {
+            continue;
+        };
+        let keyword = child.child(0).map(|k| k.grammar_name()).unwrap_or_default();
+        res.push(DocumentSymbol {
+            name: doc.node_content(name_node),
+            detail: Some(keyword.to_string()),
+            kind,
+            tags: None,
+            deprecated: None,
+            range: child.range().to_lsp_range(),
+            selection_range: name_node.range().to_lsp_range(),
+            children: None,
+        });
+    }
 }
 
 pub fn symbols(uri: &Url, doc: &Document) -> Vec<SymbolInformation> {
@@ -56,6 +153,25 @@ pub fn symbols(uri: &Url, doc: &Document) -> Vec<SymbolInformation> {
     res
 }
 
+/// The IMPORT aliases, so the imported earthfiles can be found by their alias in the workspace symbols
+pub fn import_symbols(uri: &Url, doc: &Document) -> Vec<SymbolInformation> {
+    let mut res = Vec::new();
+    for import in import::imports(doc) {
+        res.push(SymbolInformation {
+            name: import.alias,
+            kind: SymbolKind::MODULE,
+            tags: None,
+            deprecated: None,
+            location: Location {
+                uri: uri.to_owned(),
+                range: import.alias_node.unwrap_or(import.earthfile_node).range().to_lsp_range(),
+            },
+            container_name: container_name(doc, import.node),
+        })
+    }
+    res
+}
+
 fn symbol_query() -> &'static Query {
     static QUERY: OnceLock<Query> = OnceLock::new();
     QUERY.get_or_init(|| {
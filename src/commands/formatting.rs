@@ -0,0 +1,65 @@
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+
+use crate::{
+    backend::Backend,
+    format::{content_end_row, format, format_units},
+    util::request_failed,
+};
+
+pub fn formatting(
+    backend: &Backend,
+    params: DocumentFormattingParams,
+) -> Result<Option<Vec<TextEdit>>> {
+    let uri = &params.text_document.uri;
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let Some(text) = format(doc, &indent(&params.options)) else {
+        return Ok(None);
+    };
+    if doc.rope == text {
+        return Ok(Some(Vec::new()));
+    }
+    let last_line = doc.rope.len_lines() - 1;
+    let end =
+        Position { line: last_line as u32, character: doc.rope.line(last_line).len_chars() as u32 };
+    Ok(Some(vec![TextEdit { range: Range { start: Position::default(), end }, new_text: text }]))
+}
+
+/// Format the top level elements — usually the targets — intersecting the range
+pub fn range_formatting(
+    backend: &Backend,
+    params: DocumentRangeFormattingParams,
+) -> Result<Option<Vec<TextEdit>>> {
+    let uri = &params.text_document.uri;
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let Some(units) = format_units(doc, &indent(&params.options)) else {
+        return Ok(None);
+    };
+    let range = params.range;
+    Ok(Some(
+        units
+            .into_iter()
+            .filter_map(|(node, new_text)| {
+                let start = node.start_position().row as u32;
+                let end = content_end_row(doc, node) as u32 + 1;
+                if end <= range.start.line || start > range.end.line {
+                    return None;
+                }
+                let original = doc.rope.slice(
+                    doc.rope.line_to_char(start as usize)..doc.rope.line_to_char(end as usize),
+                );
+                if original == new_text.as_str() {
+                    return None;
+                }
+                let range = Range {
+                    start: Position { line: start, character: 0 },
+                    end: Position { line: end, character: 0 },
+                };
+                Some(TextEdit { range, new_text })
+            })
+            .collect(),
+    ))
+}
+
+fn indent(options: &FormattingOptions) -> String {
+    crate::format::indent(options.tab_size, options.insert_spaces)
+}
@@ -1,11 +1,13 @@
-use std::sync::OnceLock;
-
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
-use tree_sitter::{Node, Point, Query};
+use tree_sitter::Point;
 
 use crate::{
     backend::Backend,
+    document::Document,
+    import,
+    reference::{reference_at, target_names},
     util::{request_failed, ToLSPRange},
+    variable,
 };
 
 pub fn goto_definition(
@@ -15,40 +17,23 @@ pub fn goto_definition(
     let pos = &params.text_document_position_params.position;
     let uri = &params.text_document_position_params.text_document.uri;
     let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
-    let root_node = doc.tree.root_node();
-    let pos = Point { row: pos.line as usize, column: 1 + pos.character as usize };
-    let mut cursor = root_node.walk();
-    let mut origin_node: Option<Node> = None;
-    while cursor.goto_first_child_for_point(pos).is_some() {
-        if ["target_ref", "function_ref"].contains(&cursor.node().grammar_name()) {
-            origin_node = Some(cursor.node());
-            break;
+    let pos = Point { row: pos.line as usize, column: pos.character as usize };
+    let Some(reference) = reference_at(doc, pos) else {
+        if let Some(res) = goto_variable(uri, doc, pos) {
+            return Ok(Some(res));
         }
-    }
-    let Some(origin_node) = origin_node else {
-        return Ok(None);
+        return goto_import(backend, uri, doc, pos);
     };
-    let Some(name_node) = origin_node.child_by_field_name("name") else {
-        return Ok(None);
-    };
-    let target_uris = if let Some(earthfile_ref_node) = origin_node.child_by_field_name("earthfile")
-    {
-        let earthfile_ref = doc.node_content(earthfile_ref_node);
-        backend.match_earthfile_ref(uri, &earthfile_ref)?
-    } else {
-        vec![uri.to_owned()]
-    };
-    let name = doc.node_content(name_node);
     let mut res = Vec::new();
-    for target_uri in target_uris {
+    for target_uri in backend.match_reference(uri, &reference)? {
         let target_doc = &backend
             .docs
             .get(&target_uri)
             .ok_or_else(|| request_failed(&format!("unknown document: {target_uri}")))?;
-        for node in target_doc.captures(target_name()) {
-            if target_doc.node_content(node) == name {
+        for node in target_names(target_doc) {
+            if target_doc.node_content(node) == reference.name {
                 res.push(LocationLink {
-                    origin_selection_range: Some(origin_node.range().to_lsp_range()),
+                    origin_selection_range: Some(reference.range.to_lsp_range()),
                     target_uri: target_uri.to_owned(),
                     target_range: node.range().to_lsp_range(), // TODO: this should probably be a different range
                     target_selection_range: node.range().to_lsp_range(),
@@ -59,9 +44,45 @@ pub fn goto_definition(
     Ok(Some(GotoDefinitionResponse::Link(res)))
 }
 
-fn target_name() -> &'static Query {
-    static QUERY: OnceLock<Query> = OnceLock::new();
-    QUERY.get_or_init(|| {
-        Query::new(&crate::parser::language(), r"(target name: (identifier) @target_name)").unwrap()
-    })
+/// Jump to the ARG, LET, ENV or FOR declaring the variable used at the given position
+fn goto_variable(uri: &Url, doc: &Document, pos: Point) -> Option<GotoDefinitionResponse> {
+    let usage = variable::usages(doc)
+        .into_iter()
+        .find(|u| u.range.start_point <= pos && pos <= u.range.end_point)?;
+    let declaration = variable::resolve(doc, &usage.name, usage.range.start_point)?;
+    Some(GotoDefinitionResponse::Link(vec![LocationLink {
+        origin_selection_range: Some(usage.range.to_lsp_range()),
+        target_uri: uri.to_owned(),
+        target_range: declaration.command.range().to_lsp_range(),
+        target_selection_range: declaration.name_node.range().to_lsp_range(),
+    }]))
+}
+
+/// Jump to the imported Earthfiles when the position is on the earthfile reference or the alias of an IMPORT command
+fn goto_import(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    pos: Point,
+) -> Result<Option<GotoDefinitionResponse>> {
+    let Some((import, origin)) = import::imports(doc).into_iter().find_map(|i| {
+        let origin = [Some(i.earthfile_node), i.alias_node]
+            .into_iter()
+            .flatten()
+            .find(|n| n.start_position() <= pos && pos <= n.end_position())?;
+        Some((i, origin))
+    }) else {
+        return Ok(None);
+    };
+    let res = backend
+        .match_earthfile_ref(uri, &import.earthfile)?
+        .into_iter()
+        .map(|target_uri| LocationLink {
+            origin_selection_range: Some(origin.range().to_lsp_range()),
+            target_uri,
+            target_range: Range::default(),
+            target_selection_range: Range::default(),
+        })
+        .collect();
+    Ok(Some(GotoDefinitionResponse::Link(res)))
 }
@@ -1,13 +1,35 @@
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
-use tree_sitter::Point;
+use tree_sitter::{Node, Point};
 
-use crate::{backend::Backend, descriptions::command_description, util::request_failed};
+use crate::{
+    backend::Backend,
+    call::declared_args,
+    descriptions::{command_description, command_options},
+    document::Document,
+    import,
+    reference::{reference_at, target_names, Reference},
+    util::{request_failed, ToLSPRange},
+    variable::{self, Declaration},
+};
 
 pub fn hover(backend: &Backend, params: HoverParams) -> Result<Option<Hover>> {
     let pos = &params.text_document_position_params.position;
     let uri = &params.text_document_position_params.text_document.uri;
-    let tree = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document"))?.tree;
-    let root_node = tree.root_node();
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document"))?;
+    let point = Point { row: pos.line as usize, column: pos.character as usize };
+    if let Some(hover) = import_alias_hover(doc, point) {
+        return Ok(Some(hover));
+    }
+    if let Some(hover) = option_hover(doc, point) {
+        return Ok(Some(hover));
+    }
+    if let Some(hover) = variable_hover(doc, point) {
+        return Ok(Some(hover));
+    }
+    if let Some(hover) = target_hover(backend, uri, doc, point)? {
+        return Ok(Some(hover));
+    }
+    let root_node = doc.tree.root_node();
     let pos = Point { row: pos.line as usize, column: 1 + pos.character as usize };
     let mut cursor = root_node.walk();
     let mut description = None;
@@ -48,3 +70,221 @@ pub fn hover(backend: &Backend, params: HoverParams) -> Result<Option<Hover>> {
         Ok(None)
     }
 }
+
+/// Show the imported earthfile reference when hovering an IMPORT alias, either in the IMPORT command or in a target
+/// reference
+fn import_alias_hover(doc: &Document, pos: Point) -> Option<Hover> {
+    let contains = |r: &tree_sitter::Range| r.start_point <= pos && pos <= r.end_point;
+    let (alias, earthfile, range) = if let Some(import) = import::imports(doc)
+        .into_iter()
+        .find(|i| i.alias_node.is_some_and(|n| contains(&n.range())))
+    {
+        (import.alias, import.earthfile, import.alias_node?.range())
+    } else {
+        let reference = reference_at(doc, pos)?;
+        let range = reference.earthfile_range?;
+        let alias = reference.earthfile_ref?;
+        if !contains(&range) || !import::is_alias(&alias) {
+            return None;
+        }
+        (alias, reference.earthfile?, range)
+    };
+    if alias == earthfile {
+        // not an actual alias
+        return None;
+    }
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("`{alias}` is imported from `{earthfile}`"),
+        }),
+        range: Some(range.to_lsp_range()),
+    })
+}
+
+/// Show the documentation of the command option at the given position
+fn option_hover(doc: &Document, pos: Point) -> Option<Hover> {
+    let mut node = doc.tree.root_node().descendant_for_point_range(pos, pos)?;
+    while node.parent()?.kind() != "options" {
+        node = node.parent()?;
+    }
+    let command = node.parent()?.parent()?;
+    let content = doc.node_content(node);
+    let name = content.split(['=', ' ', '\t']).next().unwrap_or_default();
+    let option = command_options(command.kind()).into_iter().find(|o| o.name == name)?;
+    let deprecated = if option.deprecated { "**Deprecated**\n\n" } else { "" };
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!(
+                "```earthfile\n{}\n```\n{deprecated}{}",
+                option.synopsis, option.description
+            ),
+        }),
+        range: Some(node.range().to_lsp_range()),
+    })
+}
+
+/// Show the declaration of the variable declared or used at the given position
+fn variable_hover(doc: &Document, pos: Point) -> Option<Hover> {
+    let declaration = variable::declaration_at(doc, pos)?;
+    // the variable under the cursor: the declared one, or a usage
+    let contains = |r: &tree_sitter::Range| r.start_point <= pos && pos <= r.end_point;
+    let range = Some(declaration.name_node.range())
+        .filter(contains)
+        .or_else(|| variable::usages(doc).into_iter().map(|u| u.range).find(contains))?;
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: variable_section(doc, &declaration),
+        }),
+        range: Some(range.to_lsp_range()),
+    })
+}
+
+fn variable_section(doc: &Document, declaration: &Declaration) -> String {
+    let keyword = declaration.keyword();
+    let target = match declaration.target().and_then(|t| t.child_by_field_name("name")) {
+        Some(name) => format!("target `{}`", doc.node_content(name)),
+        None => "the base target".to_string(),
+    };
+    let mut res = format!(
+        "```earthfile\n{}\n```\nDeclared with `{keyword}` in {target}",
+        doc.node_content(declaration.command).trim()
+    );
+    let mut properties: Vec<String> = declaration
+        .command
+        .child_by_field_name("options")
+        .map(|options| {
+            let mut cursor = options.walk();
+            let res = options
+                .children(&mut cursor)
+                .map(|o| doc.node_content(o))
+                .filter(|o| ["--global", "--required", "--secret"].contains(&o.as_str()))
+                .map(|o| o.trim_start_matches('-').to_owned())
+                .collect();
+            res
+        })
+        .unwrap_or_default();
+    if let Some(value) = declaration.value() {
+        let name = if keyword == "ARG" { "default value" } else { "value" };
+        properties.push(format!("{name}: `{}`", doc.node_content(value)));
+    }
+    if !properties.is_empty() {
+        res.push('\n');
+        for property in properties {
+            res.push_str(&format!("\n* {property}"));
+        }
+    }
+    res
+}
+
+/// Show the definition of the target referenced at the given position: its Earthfile, its documentation, its ARGs and
+/// its outputs
+fn target_hover(backend: &Backend, uri: &Url, doc: &Document, pos: Point) -> Result<Option<Hover>> {
+    let Some(reference) = reference_at(doc, pos) else {
+        return Ok(None);
+    };
+    let mut sections = Vec::new();
+    for target_uri in backend.match_reference(uri, &reference)? {
+        let section = if &target_uri == uri {
+            target_section(backend, &target_uri, doc, &reference.name)
+        } else if let Some(target_doc) = backend.docs.get(&target_uri) {
+            target_section(backend, &target_uri, &target_doc, &reference.name)
+        } else {
+            None
+        };
+        sections.extend(section);
+    }
+    if sections.is_empty() {
+        return Ok(remote_hover(backend, &reference));
+    }
+    Ok(Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: sections.join("\n\n---\n\n"),
+        }),
+        range: Some(reference.range.to_lsp_range()),
+    }))
+}
+
+/// Explain how to browse a remote target that has no local checkout
+fn remote_hover(backend: &Backend, reference: &Reference) -> Option<Hover> {
+    let earthfile = reference.earthfile.as_deref()?;
+    if !import::is_remote(earthfile) || backend.remote_earthfile_dir(earthfile).is_some() {
+        return None;
+    }
+    let (path, git_ref) = import::split_git_ref(earthfile);
+    let version = git_ref.map(|r| format!(" at `{r}`")).unwrap_or_default();
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!(
+                "`+{}` is defined in the remote Earthfile `{path}`{version}.\n\nMap its repository to a local \
+                 checkout with the `remoteEarthfiles` initialization option to browse it.",
+                reference.name
+            ),
+        }),
+        range: Some(reference.range.to_lsp_range()),
+    })
+}
+
+fn target_section(backend: &Backend, uri: &Url, doc: &Document, name: &str) -> Option<String> {
+    let target = target_names(doc)
+        .into_iter()
+        .find(|n| doc.node_content(*n) == name)
+        .and_then(|n| n.parent())?;
+    let mut res =
+        format!("```earthfile\n{name}:\n```\nDefined in `{}`", backend.workspace_path(uri));
+    let comment = leading_comment(doc, target);
+    if !comment.is_empty() {
+        res.push_str(&format!("\n\n{comment}"));
+    }
+    let args = declared_args(doc, target);
+    if !args.is_empty() {
+        res.push_str("\n\n**ARGs**\n");
+        for arg in args {
+            res.push_str(&format!("\n* `--{}` — {}", arg.name, arg.detail()));
+        }
+    }
+    let mut outputs = Vec::new();
+    save_commands(target, &mut outputs);
+    if !outputs.is_empty() {
+        res.push_str("\n\n**Outputs**\n");
+        for output in outputs {
+            res.push_str(&format!("\n* `{}`", doc.node_content(output).trim()));
+        }
+    }
+    Some(res)
+}
+
+/// The comment lines right above a target, without their leading `#`
+fn leading_comment(doc: &Document, target: Node) -> String {
+    let mut lines = Vec::new();
+    let mut next = target;
+    while let Some(prev) = next.prev_sibling() {
+        if prev.kind() != "comment" || prev.end_position().row != next.start_position().row {
+            break;
+        }
+        let content = doc.node_content(prev);
+        let line = content.trim().trim_start_matches('#');
+        lines.push(line.strip_prefix(' ').unwrap_or(line).to_owned());
+        next = prev;
+    }
+    lines.reverse();
+    lines.join("\n")
+}
+
+/// The SAVE IMAGE and SAVE ARTIFACT commands of a target, including the ones in nested blocks
+fn save_commands<'doc>(node: Node<'doc>, res: &mut Vec<Node<'doc>>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "save_image_command" | "save_artifact_command" => res.push(child),
+            kind if kind == "block" || kind.ends_with("_command") || kind.ends_with("_block") => {
+                save_commands(child, res)
+            }
+            _ => (),
+        }
+    }
+}
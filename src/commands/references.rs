@@ -1,150 +1,126 @@
-use std::sync::OnceLock;
-
-use clean_path::Clean;
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
-use tree_sitter::{Point, Query, QueryCursor};
+use tree_sitter::Point;
 
 use crate::{
     backend::Backend,
-    util::{is_earthfile_ref_match, request_failed, RopeProvider, ToLSPRange},
+    reference::{reference_at, references as doc_references, target_name_at, target_names},
+    util::{request_failed, ToLSPRange},
+    variable,
 };
 
+/// A place where a target is declared or referenced
+#[derive(Debug, Clone)]
+pub struct TargetLocation {
+    pub uri: Url,
+    /// the range of the whole reference, or of the target name for a declaration
+    pub range: Range,
+    /// the range of the target name only
+    pub name_range: Range,
+    pub is_declaration: bool,
+    /// the earthfile part of a reference, if any
+    pub earthfile_ref: Option<String>,
+}
+
 pub fn references(backend: &Backend, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
-    let Some((target_uri, target_name)) = get_target(backend, &params)? else {
-        return Ok(None);
-    };
+    let uri = &params.text_document_position.text_document.uri;
+    let pos = params.text_document_position.position;
     let include_declaration = params.context.include_declaration;
+    let Some((target_uri, target_name)) = target_at(backend, uri, pos)? else {
+        return variable_references(backend, uri, pos, include_declaration);
+    };
+    Ok(Some(
+        target_locations(backend, &target_uri, &target_name)?
+            .into_iter()
+            .filter(|l| include_declaration || !l.is_declaration)
+            .map(|l| Location { uri: l.uri, range: l.range })
+            .collect(),
+    ))
+}
 
-    // some query stuff
-    let query = if include_declaration { target_and_ref_query() } else { ref_query() };
-    let ref_idx = query.capture_index_for_name("ref").unwrap();
-    let target_earthfile_idx = query.capture_index_for_name("target_earthfile").unwrap();
-    let target_name_idx = query.capture_index_for_name("target_name").unwrap();
-    let mut query_cursor = QueryCursor::new();
+/// The references to a variable are limited to the document declaring it
+fn variable_references(
+    backend: &Backend,
+    uri: &Url,
+    pos: Position,
+    include_declaration: bool,
+) -> Result<Option<Vec<Location>>> {
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let pos = Point { row: pos.line as usize, column: pos.character as usize };
+    let Some(declaration) = variable::declaration_at(doc, pos) else {
+        return Ok(None);
+    };
+    let declaration_range = include_declaration.then(|| declaration.name_node.range()).into_iter();
+    Ok(Some(
+        declaration_range
+            .chain(variable::declaration_usages(doc, &declaration).into_iter().map(|u| u.range))
+            .map(|range| Location { uri: uri.to_owned(), range: range.to_lsp_range() })
+            .collect(),
+    ))
+}
 
-    // now search in all the known documents to find some references to that target in that earthfile
-    let mut res: Vec<Location> = Vec::new();
+/// Search in all the known documents to find the declarations of and the references to a target in an earthfile
+pub fn target_locations(
+    backend: &Backend,
+    target_uri: &Url,
+    target_name: &str,
+) -> Result<Vec<TargetLocation>> {
+    let mut res = Vec::new();
     for item in backend.docs.iter() {
         let other_uri = item.key();
         let other_doc = item.value();
-        let matches = query_cursor.matches(
-            query,
-            other_doc.tree.root_node(),
-            RopeProvider(other_doc.rope.slice(..)),
-        );
-        for m in matches {
-            // extract the target name from the capture
-            let Some(name_capture) = m.captures.iter().find(|c| c.index == target_name_idx) else {
-                continue;
-            };
-            let ref_name = other_doc.node_content(name_capture.node);
-
-            // extract the earthfile uri
-            let earthfile_capture = m.captures.iter().find(|c| c.index == target_earthfile_idx);
-            let earthfile_ref = if let Some(earthfile_capture) = earthfile_capture {
-                other_doc.node_content(earthfile_capture.node)
-            } else {
-                "./".to_owned()
-            };
-            if is_earthfile_ref_match(other_uri, &earthfile_ref, &target_uri)?
-                && ref_name == target_name
+        if backend.is_earthfile_ref_match(other_uri, "./", target_uri)? {
+            for node in target_names(other_doc) {
+                if other_doc.node_content(node) == target_name {
+                    res.push(TargetLocation {
+                        uri: other_uri.to_owned(),
+                        range: node.range().to_lsp_range(),
+                        name_range: node.range().to_lsp_range(),
+                        is_declaration: true,
+                        earthfile_ref: None,
+                    });
+                }
+            }
+        }
+        for r in doc_references(other_doc) {
+            let earthfile_ref = r.earthfile.as_deref().unwrap_or("./");
+            if r.name == target_name
+                && backend.is_earthfile_ref_match(other_uri, earthfile_ref, target_uri)?
             {
-                let range =
-                    if let Some(ref_capture) = m.captures.iter().find(|c| c.index == ref_idx) {
-                        ref_capture.node.range()
-                    } else {
-                        name_capture.node.range()
-                    };
-
-                res.push(Location { uri: other_uri.to_owned(), range: range.to_lsp_range() });
+                res.push(TargetLocation {
+                    uri: other_uri.to_owned(),
+                    range: r.range.to_lsp_range(),
+                    name_range: r.name_range.to_lsp_range(),
+                    is_declaration: false,
+                    earthfile_ref: r.earthfile.clone(),
+                });
             }
         }
     }
-    Ok(Some(res))
+    Ok(res)
 }
 
-fn get_target(backend: &Backend, params: &ReferenceParams) -> Result<Option<(Url, String)>> {
-    let pos = &params.text_document_position.position;
-    let uri = &params.text_document_position.text_document.uri;
+/// Find the target declared or referenced at the given position, as its earthfile uri and its name
+pub fn target_at(backend: &Backend, uri: &Url, pos: Position) -> Result<Option<(Url, String)>> {
     let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
     let pos = Point { row: pos.line as usize, column: pos.character as usize };
 
-    // some query stuff
-    let query = target_and_ref_query();
-    let ref_idx = query.capture_index_for_name("ref").unwrap();
-    let target_earthfile_idx = query.capture_index_for_name("target_earthfile").unwrap();
-    let target_name_idx = query.capture_index_for_name("target_name").unwrap();
-
-    // find the query match at the given position
-    let mut query_cursor = QueryCursor::new();
-    let mut matches =
-        query_cursor.matches(query, doc.tree.root_node(), RopeProvider(doc.rope.slice(..)));
-    let Some(m) = matches.find(|m| {
-        let node = m
-            .nodes_for_capture_index(ref_idx)
-            .next()
-            .or_else(|| m.nodes_for_capture_index(target_name_idx).next())
-            .unwrap();
-        node.start_position() <= pos && pos < node.end_position()
-    }) else {
-        return Ok(None);
-    };
-
-    // extract the target name from the capture
-    let Some(name_capture) = m.captures.iter().find(|c| c.index == target_name_idx) else {
+    // the position is either on a target declaration or on a reference
+    if let Some(node) = target_name_at(doc, pos) {
+        return Ok(Some((uri.to_owned(), doc.node_content(node))));
+    }
+    let Some(reference) = reference_at(doc, pos) else {
         return Ok(None);
     };
-    let target_name = doc.node_content(name_capture.node);
 
     // extract the earthfile uri
-    let earthfile_capture = m.captures.iter().find(|c| c.index == target_earthfile_idx);
-    let target_uri = if let Some(earthfile_capture) = earthfile_capture {
-        let earthfile = doc.node_content(earthfile_capture.node);
-        let path =
-            uri.to_file_path().map_err(|_| request_failed("can't compute the earthfile path"))?;
-        let path = path
-            .parent()
-            .ok_or_else(|| request_failed("can't compute the current Earthfile parent"))?;
-        let path = path.join(earthfile).join("Earthfile").clean();
-        Url::from_file_path(path)
+    let target_uri = if let Some(earthfile) = reference.earthfile {
+        let Some(dir) = backend.earthfile_ref_dir(uri, &earthfile)? else {
+            return Ok(None);
+        };
+        Url::from_file_path(dir.join("Earthfile"))
             .map_err(|_| request_failed("can't convert the earthfile path to an url"))?
     } else {
         uri.to_owned()
     };
-    Ok(Some((target_uri, target_name)))
-}
-
-pub fn target_and_ref_query() -> &'static Query {
-    static QUERY: OnceLock<Query> = OnceLock::new();
-    QUERY.get_or_init(|| {
-        Query::new(
-            &crate::parser::language(),
-            r"(target_ref
-                earthfile: (earthfile_ref)? @target_earthfile
-                name: (identifier) @target_name) @ref
-              (function_ref
-                earthfile: (earthfile_ref)? @target_earthfile
-                name: (identifier) @target_name) @ref
-              (target name: (identifier) @target_name)
-              ",
-        )
-        .unwrap()
-    })
-}
-
-pub fn ref_query() -> &'static Query {
-    static QUERY: OnceLock<Query> = OnceLock::new();
-    QUERY.get_or_init(|| {
-        Query::new(
-            &crate::parser::language(),
-            r"(target_ref
-                earthfile: (earthfile_ref)? @target_earthfile
-                name: (identifier) @target_name) @ref
-              (function_ref
-                earthfile: (earthfile_ref)? @target_earthfile
-                name: (identifier) @target_name) @ref
-              ",
-        )
-        .unwrap()
-    })
+    Ok(Some((target_uri, reference.name)))
 }
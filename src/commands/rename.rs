@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+use tree_sitter::Point;
+
+use crate::{
+    backend::Backend,
+    commands::references::{target_at, target_locations},
+    reference::{is_function_target, reference_at, target_name_at, target_names},
+    util::{request_failed, ToLSPRange},
+};
+
+pub fn prepare_rename(
+    backend: &Backend,
+    params: TextDocumentPositionParams,
+) -> Result<Option<PrepareRenameResponse>> {
+    let uri = &params.text_document.uri;
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let pos =
+        Point { row: params.position.line as usize, column: params.position.character as usize };
+    let (range, name) = if let Some(node) = target_name_at(doc, pos) {
+        (node.range(), doc.node_content(node))
+    } else if let Some(reference) = reference_at(doc, pos) {
+        (reference.name_range, reference.name)
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(PrepareRenameResponse::RangeWithPlaceholder {
+        range: range.to_lsp_range(),
+        placeholder: name,
+    }))
+}
+
+pub fn rename(backend: &Backend, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+    let new_name = &params.new_name;
+    let Some((target_uri, target_name)) = target_at(
+        backend,
+        &params.text_document_position.text_document.uri,
+        params.text_document_position.position,
+    )?
+    else {
+        return Ok(None);
+    };
+    // the remote documents are local checkouts, only there to resolve the references to them
+    if backend.is_remote_doc(&target_uri) {
+        return Err(request_failed(&format!(
+            "can't rename {target_name}: it is declared in a remote Earthfile"
+        )));
+    }
+    let function = is_function(backend, &target_uri, &target_name);
+    if !is_valid_target_name(new_name, function) {
+        let kind = if function { "function" } else { "target" };
+        return Err(request_failed(&format!("invalid {kind} name: {new_name}")));
+    }
+    let locations = target_locations(backend, &target_uri, &target_name)?;
+    // a wildcard reference can't be renamed for a single Earthfile
+    for l in &locations {
+        let Some(earthfile_ref) = l.earthfile_ref.as_deref().filter(|r| is_wildcard(r)) else {
+            continue;
+        };
+        if backend.match_earthfile_ref(&l.uri, earthfile_ref)?.iter().any(|u| u != &target_uri) {
+            return Err(request_failed(&format!(
+                "can't rename {target_name}: the wildcard reference {earthfile_ref}+{target_name} in {} also \
+                 matches other Earthfiles",
+                backend.workspace_path(&l.uri)
+            )));
+        }
+    }
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for l in locations.into_iter().filter(|l| !backend.is_remote_doc(&l.uri)) {
+        changes
+            .entry(l.uri)
+            .or_default()
+            .push(TextEdit { range: l.name_range, new_text: new_name.to_owned() });
+    }
+    Ok(Some(WorkspaceEdit { changes: Some(changes), ..Default::default() }))
+}
+
+fn is_wildcard(earthfile_ref: &str) -> bool {
+    earthfile_ref.contains(['*', '?', '['])
+}
+
+/// Whether the renamed target is a function, from its declaration — or from its name when it is not declared
+fn is_function(backend: &Backend, uri: &Url, name: &str) -> bool {
+    let declared = backend.docs.get(uri).and_then(|doc| {
+        target_names(&doc)
+            .into_iter()
+            .find(|n| doc.node_content(*n) == name)
+            .and_then(|n| n.parent())
+            .map(is_function_target)
+    });
+    declared.unwrap_or_else(|| name.starts_with(|c: char| c.is_ascii_uppercase()))
+}
+
+/// Check the name against the grammar identifier rule, and the earthly naming rules: the functions are in upper case,
+/// and the targets start with a lower case letter
+fn is_valid_target_name(name: &str, function: bool) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    let valid_chars = chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if function {
+        valid_chars
+            && first.is_ascii_uppercase()
+            && !name.contains(|c: char| c.is_ascii_lowercase())
+    } else {
+        valid_chars && first.is_ascii_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_target_name;
+
+    #[test]
+    fn should_validate_target_name() {
+        assert!(is_valid_target_name("build", false));
+        assert!(is_valid_target_name("build-all.v2", false));
+        assert!(is_valid_target_name("buildAll", false));
+        assert!(!is_valid_target_name("", false));
+        assert!(!is_valid_target_name("2build", false));
+        assert!(!is_valid_target_name("-build", false));
+        assert!(!is_valid_target_name("_build", false));
+        assert!(!is_valid_target_name("build all", false));
+        assert!(!is_valid_target_name("+build", false));
+        assert!(!is_valid_target_name("UPPER", false));
+    }
+
+    #[test]
+    fn should_validate_function_name() {
+        assert!(is_valid_target_name("MY_FUNC", true));
+        assert!(is_valid_target_name("FUNC2", true));
+        assert!(!is_valid_target_name("", true));
+        assert!(!is_valid_target_name("lower", true));
+        assert!(!is_valid_target_name("My_Func", true));
+        assert!(!is_valid_target_name("_FUNC", true));
+        assert!(!is_valid_target_name("MY FUNC", true));
+    }
+}
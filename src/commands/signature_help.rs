@@ -0,0 +1,64 @@
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+
+use crate::{
+    backend::Backend,
+    call::{called_target, target_args, typed_command, TypedCommand},
+    util::request_failed,
+};
+
+pub fn signature_help(
+    backend: &Backend,
+    params: SignatureHelpParams,
+) -> Result<Option<SignatureHelp>> {
+    let pos = params.text_document_position_params.position;
+    let uri = &params.text_document_position_params.text_document.uri;
+    let doc = &backend.docs.get(uri).ok_or_else(|| request_failed("unknown document: {uri}"))?;
+    let Some(TypedCommand { keyword, args, current }) = typed_command(doc, pos) else {
+        return Ok(None);
+    };
+    if !["BUILD", "COPY", "DO", "FROM"].contains(&keyword) {
+        return Ok(None);
+    }
+    let Some((earthfile_ref, name)) = called_target(&args) else {
+        return Ok(None);
+    };
+    let target_args = target_args(backend, uri, doc, pos, earthfile_ref, name)?;
+
+    // build the label and keep track of the position of each parameter in it, in UTF-16 code units like the LSP
+    // positions
+    let mut label = format!("{earthfile_ref}+{name}");
+    let mut parameters = Vec::new();
+    for arg in &target_args {
+        label.push(' ');
+        let start = label.encode_utf16().count() as u32;
+        label.push_str(&format!("--{}", arg.name));
+        if let Some(value) = &arg.default_value {
+            label.push_str(&format!("={value}"));
+        }
+        parameters.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([start, label.encode_utf16().count() as u32]),
+            documentation: Some(Documentation::String(arg.detail())),
+        });
+    }
+    let typed_name = current.trim_start_matches('-').split('=').next().unwrap_or_default();
+    // the parameter being typed: the one with the typed name, or else the first one starting with it
+    let active_parameter = (!typed_name.is_empty())
+        .then(|| {
+            target_args
+                .iter()
+                .position(|a| a.name == typed_name)
+                .or_else(|| target_args.iter().position(|a| a.name.starts_with(typed_name)))
+        })
+        .flatten()
+        .map(|i| i as u32);
+    Ok(Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: None,
+            parameters: Some(parameters),
+            active_parameter,
+        }],
+        active_signature: Some(0),
+        active_parameter,
+    }))
+}
@@ -2,18 +2,121 @@ use tower_lsp::{jsonrpc::Result, lsp_types::*};
 
 use crate::backend::Backend;
 
-use super::document_symbol::symbols;
+use super::document_symbol::{import_symbols, symbols};
+
+/// The maximum number of symbols returned, to keep the response usable in large workspaces
+const MAX_SYMBOLS: usize = 100;
 
 pub fn symbol(
     backend: &Backend,
     params: WorkspaceSymbolParams,
 ) -> Result<Option<Vec<SymbolInformation>>> {
-    Ok(Some(
-        backend
-            .docs
-            .iter()
-            .flat_map(|item| symbols(item.key(), item.value()))
-            .filter(|si| si.name.contains(&params.query))
-            .collect(),
-    ))
+    // a query like `api+docker` searches the `docker` symbols in the earthfiles matching `api`
+    let (dir_query, name_query) = match params.query.split_once('+') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, params.query.as_str()),
+    };
+    let mut res = Vec::new();
+    for item in backend.docs.iter() {
+        if backend.is_remote_doc(item.key()) {
+            continue;
+        }
+        let dir = backend.workspace_dir(item.key());
+        let dir_score = match dir_query {
+            Some(dir_query) => match fuzzy_score(dir_query, &dir) {
+                Some(score) => score,
+                None => continue,
+            },
+            None => 0,
+        };
+        let symbols = symbols(item.key(), item.value())
+            .into_iter()
+            .chain(import_symbols(item.key(), item.value()));
+        for mut si in symbols {
+            let Some(score) = fuzzy_score(name_query, &si.name) else {
+                continue;
+            };
+            si.container_name = Some(match si.container_name {
+                Some(target) => format!("{dir}+{target}"),
+                None => dir.clone(),
+            });
+            res.push((score + dir_score, si));
+        }
+    }
+    res.sort_by(|(score1, si1), (score2, si2)| {
+        score2.cmp(score1).then_with(|| si1.name.cmp(&si2.name))
+    });
+    Ok(Some(res.into_iter().take(MAX_SYMBOLS).map(|(_, si)| si).collect()))
+}
+
+/// A case insensitive fuzzy match of the query characters, in order, in the candidate. The higher the score, the
+/// better the match: consecutive characters, and characters at the start of a word are favored. Returns `None` when
+/// the candidate doesn't match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let is_boundary = |i: usize| {
+        i == 0
+            || !chars[i - 1].is_alphanumeric()
+            || (chars[i - 1].is_lowercase() && chars[i].is_uppercase())
+    };
+    // best[i] is the best score of the query prefix matched so far, with its last character matched at i
+    let mut best: Vec<Option<i64>> = lower
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (*c == query[0]).then(|| 1 + if is_boundary(i) { 10 } else { 0 }))
+        .collect();
+    for q in &query[1..] {
+        let mut next = vec![None; chars.len()];
+        for i in 0..chars.len() {
+            if lower[i] != *q {
+                continue;
+            }
+            next[i] = (0..i)
+                .filter_map(|p| {
+                    let prev = best[p]?;
+                    let bonus = if p + 1 == i {
+                        5
+                    } else if is_boundary(i) {
+                        10
+                    } else {
+                        // skipping some characters in the middle of a word
+                        -((i - p - 1).min(5) as i64)
+                    };
+                    Some(prev + 1 + bonus)
+                })
+                .max();
+        }
+        best = next;
+    }
+    let mut score = best.into_iter().flatten().max()?;
+    if candidate.to_lowercase() == query.iter().collect::<String>() {
+        score += 100;
+    }
+    // favor the shortest candidates
+    Some(score * 10 - chars.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_score;
+
+    #[test]
+    fn should_fuzzy_match() {
+        assert_eq!(fuzzy_score("", "docker"), Some(0));
+        assert!(fuzzy_score("dkr", "docker").is_some());
+        assert!(fuzzy_score("DOCK", "docker").is_some());
+        assert!(fuzzy_score("rkd", "docker").is_none());
+        // exact matches first
+        assert!(fuzzy_score("docker", "docker") > fuzzy_score("docker", "docker-image"));
+        // then the word boundaries
+        assert!(fuzzy_score("di", "docker-image") > fuzzy_score("di", "dind"));
+        assert!(fuzzy_score("bi", "buildImage") > fuzzy_score("bi", "bin"));
+        // then the consecutive characters
+        assert!(fuzzy_score("doc", "docker") > fuzzy_score("doc", "dxoxc"));
+    }
 }
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower_lsp::{jsonrpc::Result, lsp_types::request::Request};
+
+use crate::{
+    backend::Backend,
+    graph::{self, GraphFormat},
+};
+
+/// The `earthlyls/targetGraph` request: the dependency graph of the targets of all the loaded Earthfiles
+pub enum TargetGraphRequest {}
+
+impl Request for TargetGraphRequest {
+    type Params = TargetGraphParams;
+    type Result = Value;
+    const METHOD: &'static str = "earthlyls/targetGraph";
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TargetGraphParams {
+    /// `json` by default
+    #[serde(default)]
+    pub format: GraphFormat,
+}
+
+/// The graph is returned as a JSON object, or as a string for the DOT and Mermaid formats
+pub fn target_graph(backend: &Backend, params: TargetGraphParams) -> Result<Value> {
+    let graph = graph::target_graph(backend)?;
+    Ok(match params.format {
+        GraphFormat::Json => serde_json::to_value(graph).unwrap(),
+        format => Value::String(graph.render(format)),
+    })
+}
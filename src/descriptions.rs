@@ -86,3 +86,89 @@ pub fn command_description(cmd: &str) -> Option<&'static str> {
         _ => None,
     }
 }
+
+/// An option of a command, documented in a ``##### `--option` `` section of the command description
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionDescription {
+    /// the option flag, like `--platform`
+    pub name: &'static str,
+    /// the option with its value, like `--platform <platform>`
+    pub synopsis: &'static str,
+    /// the content of the option section
+    pub description: &'static str,
+    pub deprecated: bool,
+}
+
+/// Extract the options documented in the description of a command
+pub fn command_options(cmd: &str) -> Vec<OptionDescription> {
+    let Some(description) = command_description(cmd) else {
+        return Vec::new();
+    };
+    // the headings, with their level, their text and their offsets in the description
+    let mut headings = Vec::new();
+    let mut in_code = false;
+    let mut offset = 0;
+    for line in description.split_inclusive('\n') {
+        if line.starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code {
+            let level = line.chars().take_while(|c| *c == '#').count();
+            if level > 0 && line[level..].starts_with(' ') {
+                headings.push((level, line[level..].trim(), offset, offset + line.len()));
+            }
+        }
+        offset += line.len();
+    }
+    let is_option = |text: &str| text.starts_with("`--") && !text.starts_with("`--<");
+    let mut res = Vec::new();
+    for (i, (level, text, _, body_start)) in headings.iter().enumerate() {
+        if !(4..=5).contains(level) || !is_option(text) {
+            continue;
+        }
+        // the section ends with the next option, or with a higher level heading
+        let body_end = headings[i + 1..]
+            .iter()
+            .find(|(level, text, _, _)| *level < 5 || text.starts_with("`-"))
+            .map(|(_, _, start, _)| *start)
+            .unwrap_or(description.len());
+        let deprecated = text.contains("deprecated");
+        for synopsis in text.split('`').skip(1).step_by(2).filter(|s| s.starts_with("--")) {
+            let name = synopsis.split([' ', '=']).next().unwrap_or(synopsis);
+            res.push(OptionDescription {
+                name,
+                synopsis,
+                description: description[*body_start..body_end].trim(),
+                deprecated,
+            });
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::command_options;
+
+    #[test]
+    fn should_extract_command_options() {
+        let options = command_options("copy_command");
+        let names: Vec<_> = options.iter().map(|o| o.name).collect();
+        assert!(names.contains(&"--dir"));
+        assert!(names.contains(&"--keep-ts"));
+        assert!(names.contains(&"--if-exists"));
+        assert!(!names.iter().any(|n| n.starts_with("--<")));
+        let chmod = options.iter().find(|o| o.name == "--chmod").unwrap();
+        assert_eq!(chmod.synopsis, "--chmod <octal-format>");
+        assert!(!chmod.deprecated);
+        assert!(options.iter().find(|o| o.name == "--build-arg").unwrap().deprecated);
+
+        let names: Vec<_> = command_options("run_command").into_iter().map(|o| o.name).collect();
+        assert!(names.contains(&"--interactive"));
+        assert!(names.contains(&"--interactive-keep"));
+        assert!(names.contains(&"--network"));
+
+        // --global is documented with a level 4 heading
+        let names: Vec<_> = command_options("arg_command").into_iter().map(|o| o.name).collect();
+        assert_eq!(names, vec!["--required", "--global"]);
+    }
+}
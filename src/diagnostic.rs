@@ -1,22 +1,115 @@
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
 
-use crate::{backend::Backend, document::Document};
+use crate::{
+    backend::Backend, diagnostic::dependency_cycle::DependencyCycles, document::Document,
+    reference::ResolvedReferences, settings::Settings,
+};
 
+pub mod dependency_cycle;
 pub mod deprecated_build_arg;
 pub mod missing_version;
 pub mod syntax_error;
+pub mod undeclared_variable;
+pub mod undefined_target;
 pub mod unknown_option;
 
 pub const SOURCE: &str = "earthlyls";
 
-pub fn doc_diagnostics(doc: &Document) -> Result<Vec<Diagnostic>> {
+/// The diagnostic code is the name of the rule producing it
+pub fn code(rule: &str) -> Option<NumberOrString> {
+    Some(NumberOrString::String(rule.to_string()))
+}
+
+/// The fix of a diagnostic, stored in its `data` field so the code action can be built from the diagnostic alone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuickFix {
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl QuickFix {
+    pub fn new(title: impl Into<String>, edits: Vec<TextEdit>) -> Self {
+        QuickFix { title: title.into(), edits }
+    }
+
+    pub fn to_data(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+
+    pub fn from_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+        serde_json::from_value(diagnostic.data.clone()?).ok()
+    }
+}
+
+/// Compute the diagnostics of a document. The references and the dependency cycles are resolved beforehand, once for
+/// all the documents.
+pub fn doc_diagnostics(
+    backend: &Backend,
+    resolved: &ResolvedReferences,
+    cycles: &DependencyCycles,
+    uri: &Url,
+    doc: &Document,
+) -> Result<Vec<Diagnostic>> {
+    let settings = backend.doc_settings(uri);
     let mut ds = Vec::new();
     ds.append(&mut deprecated_build_arg::deprecated_build_arg(doc)?);
     ds.append(&mut unknown_option::unknown_option(doc)?);
     ds.append(&mut syntax_error::syntax_error(doc)?);
-    ds.append(&mut missing_version::missing_version(doc)?);
-    Ok(ds)
+    ds.append(&mut missing_version::missing_version(doc, &settings.earthly_version)?);
+    ds.append(&mut undefined_target::undefined_target(resolved, uri, doc)?);
+    ds.append(&mut undeclared_variable::undeclared_variable(backend, uri, doc)?);
+    ds.append(&mut dependency_cycle::dependency_cycle(backend, cycles, uri)?);
+    Ok(suppress(doc, apply_settings(&settings, ds)))
+}
+
+/// Remove the diagnostics of the disabled rules, and set the severity configured for the others
+fn apply_settings(settings: &Settings, ds: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    ds.into_iter()
+        .filter_map(|mut d| {
+            let Some(NumberOrString::String(rule)) = &d.code else {
+                return Some(d);
+            };
+            let rule = settings.rule(rule);
+            if let Some(severity) = rule.severity {
+                d.severity = Some(severity.into());
+            }
+            rule.is_enabled().then_some(d)
+        })
+        .collect()
+}
+
+/// Remove the diagnostics suppressed with an `# earthlyls-ignore: rule, other-rule` comment on the preceding line. A
+/// comment without rules suppresses all the diagnostics of the next line.
+fn suppress(doc: &Document, ds: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    ds.into_iter()
+        .filter(|d| {
+            let line = d.range.start.line as usize;
+            if line == 0 || line > doc.rope.len_lines() {
+                return true;
+            }
+            let text = doc.rope.line(line - 1).to_string();
+            let Some(rules) = text
+                .trim()
+                .strip_prefix('#')
+                .and_then(|c| c.trim_start().strip_prefix("earthlyls-ignore"))
+            else {
+                return true;
+            };
+            let rules = rules.trim();
+            if rules.is_empty() {
+                return false;
+            }
+            let Some(rules) = rules.strip_prefix(':') else {
+                return true;
+            };
+            let Some(NumberOrString::String(code)) = &d.code else {
+                return true;
+            };
+            !rules.split(',').any(|r| r.trim().replace('-', "_") == *code)
+        })
+        .collect()
 }
 
 pub async fn publish_diagnostics(backend: &Backend) -> Result<()> {
@@ -24,22 +117,38 @@ pub async fn publish_diagnostics(backend: &Backend) -> Result<()> {
     // a dashmap element during an await call — it may lead to a dead lock
     // it may be interesting to look at alternatives like scc, memo_map, c-map, async-map, …
     // see: https://github.com/xacrimon/dashmap/issues/150
+    // the diagnostics may look at the other documents, so they are computed with a shared access to the documents,
+    // and only then stored in the documents
+    let resolved = ResolvedReferences::new(backend)?;
+    let cycles = DependencyCycles::new(backend, &resolved);
+    // the remote documents are only there to resolve the references to them
     let res = backend
         .docs
-        .par_iter_mut()
-        .map(|mut item| {
-            let uri = item.key().to_owned();
-            let ds = doc_diagnostics(item.value())?;
-            if ds != item.diagnostics {
-                item.diagnostics.clone_from(&ds);
-                Ok(Some((uri, ds)))
+        .par_iter()
+        .filter(|item| !backend.is_remote_doc(item.key()))
+        .map(|item| {
+            Ok((
+                item.key().to_owned(),
+                doc_diagnostics(backend, &resolved, &cycles, item.key(), item.value())?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut res: Vec<_> = res
+        .into_iter()
+        .filter_map(|(uri, ds)| {
+            let mut doc = backend.docs.get_mut(&uri)?;
+            if ds != doc.diagnostics {
+                doc.diagnostics.clone_from(&ds);
+                Some((uri, ds))
             } else {
-                Ok(None)
+                None
             }
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect();
+    // publish in a stable order
+    res.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-    for (uri, ds) in res.into_iter().flatten() {
+    for (uri, ds) in res {
         backend.client.publish_diagnostics(uri, ds, None).await;
     }
     Ok(())
@@ -0,0 +1,288 @@
+use std::collections::{HashMap, VecDeque};
+
+use rayon::prelude::*;
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+
+use crate::{
+    backend::Backend,
+    graph::{earthfile_dir, node_id, target_edges, TargetEdge},
+    reference::ResolvedReferences,
+};
+
+type Target = (Url, String);
+
+/// A reference in a cycle, with the uri of the document making it
+type Hop = (Url, TargetEdge);
+
+/// The dependency cycles between the targets of all the known Earthfiles. The target graph is built once and shared
+/// by the diagnostics of all the documents.
+#[derive(Debug, Default)]
+pub struct DependencyCycles {
+    /// a cycle for each reference between two targets of the same strongly connected component of the target graph,
+    /// starting with that reference
+    cycles: Vec<Vec<Hop>>,
+}
+
+impl DependencyCycles {
+    pub fn new(backend: &Backend, resolved: &ResolvedReferences) -> Self {
+        let mut uris: Vec<Url> = backend.docs.iter().map(|item| item.key().to_owned()).collect();
+        uris.sort();
+        let edges = uris
+            .par_iter()
+            .map(|uri| match backend.docs.get(uri) {
+                Some(doc) => target_edges(resolved, uri, &doc)
+                    .into_iter()
+                    .map(|edge| (uri.to_owned(), edge))
+                    .collect(),
+                None => Vec::new(),
+            })
+            .collect::<Vec<Vec<Hop>>>()
+            .concat();
+        let graph = TargetGraph::new(&edges);
+        let mut component_of = vec![0; graph.nodes.len()];
+        for (i, nodes) in graph.components().iter().enumerate() {
+            for node in nodes {
+                component_of[*node] = i;
+            }
+        }
+        // the edges ending in the same target share the search of the paths from that target
+        let mut searches: HashMap<usize, HashMap<usize, Option<usize>>> = HashMap::new();
+        let cycles = (0..graph.edges.len())
+            .filter_map(|edge| {
+                let (start, end) = graph.edges[edge];
+                if component_of[start] != component_of[end] {
+                    return None;
+                }
+                let parents =
+                    searches.entry(end).or_insert_with(|| graph.search(&component_of, end));
+                graph.cycle(parents, edge)
+            })
+            .map(|cycle| cycle.into_iter().map(|i| edges[i].to_owned()).collect())
+            .collect();
+        DependencyCycles { cycles }
+    }
+}
+
+/// Report the references of a document that are part of a dependency cycle between targets, in the same Earthfile or
+/// across Earthfiles
+pub fn dependency_cycle(
+    backend: &Backend,
+    cycles: &DependencyCycles,
+    uri: &Url,
+) -> Result<Vec<Diagnostic>> {
+    let id = |uri: &Url, name: &str| node_id(&earthfile_dir(backend, uri), name);
+    let mut res = Vec::new();
+    for cycle in &cycles.cycles {
+        let Some(((edge_uri, edge), path)) = cycle.split_first() else {
+            continue;
+        };
+        if edge_uri != uri {
+            continue;
+        }
+        let mut ids = vec![id(uri, &edge.from), id(&edge.to_uri, &edge.to)];
+        ids.extend(path.iter().map(|(_, hop)| id(&hop.to_uri, &hop.to)));
+        let related = path
+            .iter()
+            .map(|(hop_uri, hop)| DiagnosticRelatedInformation {
+                location: Location { uri: hop_uri.to_owned(), range: hop.range },
+                message: format!(
+                    "{} {} in {}",
+                    hop.kind.keyword(),
+                    id(&hop.to_uri, &hop.to),
+                    id(hop_uri, &hop.from)
+                ),
+            })
+            .collect();
+        res.push(Diagnostic {
+            range: edge.range,
+            message: format!("dependency cycle: {}", ids.join(" -> ")),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some(super::SOURCE.to_string()),
+            code: super::code("dependency_cycle"),
+            related_information: Some(related),
+            ..Default::default()
+        });
+    }
+    res.sort_by_key(|d| (d.range.start.line, d.range.start.character));
+    Ok(res)
+}
+
+/// The target graph, with the targets and the references as indices
+struct TargetGraph {
+    nodes: Vec<Target>,
+    /// the source and destination nodes of each edge
+    edges: Vec<(usize, usize)>,
+    /// the edges starting from each node
+    out_edges: Vec<Vec<usize>>,
+}
+
+impl TargetGraph {
+    fn new(hops: &[Hop]) -> Self {
+        let mut index: HashMap<Target, usize> = HashMap::new();
+        let mut nodes = Vec::new();
+        let mut node = |target: Target| {
+            *index.entry(target.clone()).or_insert_with(|| {
+                nodes.push(target);
+                nodes.len() - 1
+            })
+        };
+        let edges: Vec<_> = hops
+            .iter()
+            .map(|(uri, edge)| {
+                let from = node((uri.to_owned(), edge.from.to_owned()));
+                let to = node((edge.to_uri.to_owned(), edge.to.to_owned()));
+                (from, to)
+            })
+            .collect();
+        let mut out_edges = vec![Vec::new(); nodes.len()];
+        for (i, (from, _)) in edges.iter().enumerate() {
+            out_edges[*from].push(i);
+        }
+        TargetGraph { nodes, edges, out_edges }
+    }
+
+    /// The strongly connected components of the graph, found with Tarjan's algorithm
+    fn components(&self) -> Vec<Vec<usize>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: vec![None; self.nodes.len()],
+            low_link: vec![0; self.nodes.len()],
+            on_stack: vec![false; self.nodes.len()],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        };
+        for node in 0..self.nodes.len() {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.components
+    }
+
+    /// Search the shortest paths from a node to the other nodes of its strongly connected component, breadth first.
+    /// The result is the edge used to reach each node.
+    fn search(&self, component_of: &[usize], from: usize) -> HashMap<usize, Option<usize>> {
+        let mut parents: HashMap<usize, Option<usize>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            for &next in &self.out_edges[node] {
+                let (_, to) = self.edges[next];
+                if component_of[to] == component_of[from] && !parents.contains_key(&to) {
+                    parents.insert(to, Some(next));
+                    queue.push_back(to);
+                }
+            }
+        }
+        parents
+    }
+
+    /// The shortest cycle starting with the given edge, as a list of edges, built with the search from the end of the
+    /// edge, or `None` when the edge is not in a cycle
+    fn cycle(&self, parents: &HashMap<usize, Option<usize>>, edge: usize) -> Option<Vec<usize>> {
+        let (start, _) = self.edges[edge];
+        if !parents.contains_key(&start) {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut current = start;
+        while let Some(Some(parent)) = parents.get(&current) {
+            path.push(*parent);
+            current = self.edges[*parent].0;
+        }
+        path.push(edge);
+        path.reverse();
+        Some(path)
+    }
+}
+
+struct Tarjan<'a> {
+    graph: &'a TargetGraph,
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    /// Visit the targets reachable from a node, depth first. The recursion is done with an explicit stack of the
+    /// nodes being visited, along with the position of the next edge to follow, so a long chain of targets can't
+    /// overflow the stack.
+    fn visit(&mut self, root: usize) {
+        let mut calls = vec![(root, 0)];
+        self.start(root);
+        while let Some((node, next_edge)) = calls.last_mut() {
+            let node = *node;
+            if let Some(&edge) = self.graph.out_edges[node].get(*next_edge) {
+                *next_edge += 1;
+                let (_, to) = self.graph.edges[edge];
+                match self.index[to] {
+                    None => {
+                        self.start(to);
+                        calls.push((to, 0));
+                    }
+                    Some(index) if self.on_stack[to] => {
+                        self.low_link[node] = self.low_link[node].min(index)
+                    }
+                    Some(_) => (),
+                }
+                continue;
+            }
+            // all the edges have been followed
+            calls.pop();
+            if let Some((parent, _)) = calls.last() {
+                self.low_link[*parent] = self.low_link[*parent].min(self.low_link[node]);
+            }
+            if Some(self.low_link[node]) == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(n) = self.stack.pop() {
+                    self.on_stack[n] = false;
+                    component.push(n);
+                    if n == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    fn start(&mut self, node: usize) {
+        self.index[node] = Some(self.next_index);
+        self.low_link[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Range, Url};
+
+    use super::TargetGraph;
+    use crate::graph::{EdgeKind, TargetEdge};
+
+    #[test]
+    fn should_find_components_of_a_long_chain() {
+        let uri = Url::parse("file:///Earthfile").unwrap();
+        let len = 100_000;
+        let hops: Vec<_> = (0..len)
+            .map(|i| {
+                let edge = TargetEdge {
+                    from: format!("t{i}"),
+                    to_uri: uri.clone(),
+                    to: format!("t{}", (i + 1) % len),
+                    kind: EdgeKind::Build,
+                    range: Range::default(),
+                };
+                (uri.clone(), edge)
+            })
+            .collect();
+        let components = TargetGraph::new(&hops).components();
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), len);
+    }
+}
@@ -3,19 +3,33 @@ use std::sync::OnceLock;
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
 use tree_sitter::Query;
 
+use super::QuickFix;
 use crate::{document::Document, util::ToLSPRange};
 
 pub fn deprecated_build_arg(doc: &Document) -> Result<Vec<Diagnostic>> {
     Ok(doc
         .captures(deprecated_build_arg_query())
         .iter()
-        .map(|node| Diagnostic {
-            range: node.range().to_lsp_range(),
-            message: "--build-arg is deprecated. Use --<build-arg-key>=<build-arg-value> instead."
-                .to_string(),
-            severity: Some(DiagnosticSeverity::WARNING),
-            source: Some(super::SOURCE.to_string()),
-            ..Default::default()
+        .map(|node| {
+            let range = node.range().to_lsp_range();
+            let fix = node.child_by_field_name("value").map(|value| {
+                let new_text = format!("--{}", doc.node_content(value));
+                QuickFix::new(
+                    format!("Replace with {new_text}"),
+                    vec![TextEdit { range, new_text }],
+                )
+            });
+            Diagnostic {
+                range,
+                message:
+                    "--build-arg is deprecated. Use --<build-arg-key>=<build-arg-value> instead."
+                        .to_string(),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some(super::SOURCE.to_string()),
+                code: super::code("deprecated_build_arg"),
+                data: fix.and_then(|fix| fix.to_data()),
+                ..Default::default()
+            }
         })
         .collect())
 }
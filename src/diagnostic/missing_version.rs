@@ -3,9 +3,10 @@ use std::sync::OnceLock;
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
 use tree_sitter::Query;
 
+use super::QuickFix;
 use crate::document::Document;
 
-pub fn missing_version(doc: &Document) -> Result<Vec<Diagnostic>> {
+pub fn missing_version(doc: &Document, version: &str) -> Result<Vec<Diagnostic>> {
     let captures = doc.captures(version_query());
     // make sure to find a VERSION command at the root of the file
     let mut ok = false;
@@ -24,6 +25,15 @@ pub fn missing_version(doc: &Document) -> Result<Vec<Diagnostic>> {
             message: "no version specified".to_string(),
             severity: Some(DiagnosticSeverity::ERROR),
             source: Some(super::SOURCE.to_string()),
+            code: super::code("missing_version"),
+            data: QuickFix::new(
+                format!("Add VERSION {version}"),
+                vec![TextEdit {
+                    range: Range::default(),
+                    new_text: format!("VERSION {version}\n"),
+                }],
+            )
+            .to_data(),
             ..Default::default()
         }])
     }
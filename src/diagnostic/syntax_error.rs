@@ -11,6 +11,8 @@ pub fn syntax_error(doc: &Document) -> Result<Vec<Diagnostic>> {
             range: range.to_lsp_range(),
             message: "syntax error".to_string(),
             severity: Some(DiagnosticSeverity::ERROR),
+            source: Some(super::SOURCE.to_string()),
+            code: super::code("syntax_error"),
             ..Default::default()
         })
         .chain(doc.bash_captures(syntax_error_query()).iter().map(|node| Diagnostic {
@@ -18,6 +20,7 @@ pub fn syntax_error(doc: &Document) -> Result<Vec<Diagnostic>> {
             message: "shell syntax error".to_string(),
             severity: Some(DiagnosticSeverity::ERROR),
             source: Some(super::SOURCE.to_string()),
+            code: super::code("syntax_error"),
             ..Default::default()
         }))
         .collect())
@@ -0,0 +1,263 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    sync::OnceLock,
+};
+
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::{
+    backend::Backend,
+    document::Document,
+    import::enclosing_target,
+    reference::{is_function_target, references},
+    util::{RopeProvider, ToLSPRange},
+    variable::{self, BUILTIN_ARGS, MAX_FROM_DEPTH},
+};
+
+/// The variables usually set in a shell environment, that can't be declared in the Earthfile
+const SHELL_VARIABLES: [&str; 22] = [
+    "BASH_SOURCE",
+    "BASH_VERSION",
+    "EUID",
+    "HOME",
+    "HOSTNAME",
+    "HOSTTYPE",
+    "IFS",
+    "LANG",
+    "LC_ALL",
+    "LINENO",
+    "OLDPWD",
+    "OPTARG",
+    "OPTIND",
+    "OSTYPE",
+    "PATH",
+    "PPID",
+    "PWD",
+    "RANDOM",
+    "REPLY",
+    "SHELL",
+    "TERM",
+    "USER",
+];
+
+pub fn undeclared_variable(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+) -> Result<Vec<Diagnostic>> {
+    // the environment of each target, computed once, and the other documents it is computed from
+    let mut base_envs: HashMap<Option<usize>, BaseEnv> = HashMap::new();
+    let mut docs = HashMap::new();
+    // the declarations are collected once, and not for each usage
+    let scopes = variable::Scopes::new(doc);
+    let declared: HashSet<&str> = scopes.declarations().iter().map(|d| d.name.as_str()).collect();
+    let mut res = Vec::new();
+    for usage in variable::usages(doc) {
+        let name = &usage.name;
+        if BUILTIN_ARGS.contains(&name.as_str())
+            || scopes.resolve(name, usage.range.start_point).is_some()
+        {
+            continue;
+        }
+        let target = variable::target_at(doc, usage.range.start_point);
+        // the functions are executed in the context of their caller, so we can't know what is available there
+        if target.is_some_and(is_function_target) {
+            continue;
+        }
+        if usage.in_shell && is_shell_declared(doc, usage.node, name) {
+            continue;
+        }
+        let base_env = base_envs
+            .entry(target.map(|t| t.id()))
+            .or_insert_with(|| base_env(backend, uri, doc, target, MAX_FROM_DEPTH, &mut docs));
+        // an image may set any variable in the environment of the RUN commands — but a variable declared elsewhere in
+        // the Earthfile is much more likely to be an ARG that was not declared again in this target
+        let from_image = usage.in_shell && base_env.from_image && !declared.contains(name.as_str());
+        if base_env.env.contains(name) || from_image {
+            continue;
+        }
+        res.push(Diagnostic {
+            range: usage.range.to_lsp_range(),
+            message: format!("undeclared variable {name}"),
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some(super::SOURCE.to_string()),
+            code: super::code("undeclared_variable"),
+            ..Default::default()
+        });
+    }
+    Ok(res)
+}
+
+/// Check whether a variable used in a shell fragment is provided by the shell, or by the RUN command
+fn is_shell_declared(doc: &Document, node: Node, name: &str) -> bool {
+    if SHELL_VARIABLES.contains(&name) || name.chars().all(|c| c.is_ascii_digit()) {
+        return true;
+    }
+    // an expansion with a default value, like ${FOO:-bar}, is meant to deal with an undefined variable
+    if node.parent().is_some_and(|p| p.child_by_field_name("operator").is_some()) {
+        return true;
+    }
+    // a variable assigned in the same shell fragment
+    let mut root = node;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    let query = shell_assignment_query();
+    let name_idx = query.capture_index_for_name("name").unwrap();
+    let mut query_cursor = QueryCursor::new();
+    for m in query_cursor.matches(query, root, RopeProvider(doc.rope.slice(..))) {
+        if m.nodes_for_capture_index(name_idx).any(|n| doc.node_content(n) == name) {
+            return true;
+        }
+    }
+    // a secret exposed to the RUN command
+    let Some(command) = doc
+        .tree
+        .root_node()
+        .descendant_for_point_range(node.start_position(), node.end_position())
+        .and_then(|n| ancestor(n, "run_command"))
+    else {
+        return false;
+    };
+    let Some(options) = command.child_by_field_name("options") else {
+        return false;
+    };
+    let mut cursor = options.walk();
+    let res = options.named_children(&mut cursor).filter(|n| n.kind() == "secret").any(|n| {
+        n.child_by_field_name("var")
+            .or_else(|| n.child_by_field_name("id"))
+            .is_some_and(|v| doc.node_content(v) == name)
+    });
+    res
+}
+
+/// The environment a target inherits from the base target and from the targets and images it is built FROM
+#[derive(Debug, Default)]
+struct BaseEnv {
+    /// the ENV variables of the base target and of the FROM targets
+    env: HashSet<String>,
+    /// whether the target is built from an image, that may set any variable
+    from_image: bool,
+}
+
+fn base_env(
+    backend: &Backend,
+    uri: &Url,
+    doc: &Document,
+    target: Option<Node>,
+    depth: usize,
+    docs: &mut HashMap<Url, Option<Rc<Document>>>,
+) -> BaseEnv {
+    let mut res = BaseEnv {
+        env: variable::inherited_env(doc, target).into_iter().map(|d| d.name).collect(),
+        from_image: false,
+    };
+    let froms = doc.captures(from_query());
+    let references = references(doc);
+    // the target, and the targets of the same Earthfile it is built FROM, already followed by inherited_env()
+    let targets = std::iter::once(target)
+        .chain(target.into_iter().flat_map(|t| variable::from_targets(doc, t)).map(Some));
+    for target in targets {
+        // a target without FROM command starts from the base target
+        if target.is_some() && !froms.iter().any(|from| enclosing_target(*from) == target) {
+            res.from_image |= is_from_image(doc, &froms, None);
+        }
+        res.from_image |= is_from_image(doc, &froms, target);
+        if depth == 0 {
+            continue;
+        }
+        for r in &references {
+            if r.earthfile.is_none()
+                || r.node.parent().map(|p| p.kind()) != Some("from_command")
+                || enclosing_target(r.node) != target
+            {
+                continue;
+            }
+            let Ok(from_uris) = backend.match_reference(uri, r) else {
+                continue;
+            };
+            for from_uri in from_uris {
+                let from_doc = if &from_uri == uri {
+                    None
+                } else {
+                    // copy the document, so no reference to the backend documents is held while computing the
+                    // diagnostics
+                    let from_doc = docs
+                        .entry(from_uri.to_owned())
+                        .or_insert_with(|| backend.docs.get(&from_uri).map(|d| Rc::new(d.clone())));
+                    let Some(from_doc) = from_doc else {
+                        continue;
+                    };
+                    Some(from_doc.clone())
+                };
+                let from_doc = from_doc.as_deref().unwrap_or(doc);
+                let Some(from_target) = crate::reference::target_names(from_doc)
+                    .into_iter()
+                    .find(|n| from_doc.node_content(*n) == r.name)
+                    .and_then(|n| n.parent())
+                else {
+                    continue;
+                };
+                res.env.extend(
+                    variable::declarations(from_doc)
+                        .into_iter()
+                        .filter(|d| {
+                            d.command.kind() == "env_command" && d.target() == Some(from_target)
+                        })
+                        .map(|d| d.name),
+                );
+                let from_env =
+                    base_env(backend, &from_uri, from_doc, Some(from_target), depth - 1, docs);
+                res.env.extend(from_env.env);
+                res.from_image |= from_env.from_image;
+            }
+        }
+    }
+    res
+}
+
+/// Whether a target is built FROM an image — except scratch, the empty image
+fn is_from_image(doc: &Document, froms: &[Node], target: Option<Node>) -> bool {
+    froms.iter().filter(|from| enclosing_target(**from) == target).any(|from| {
+        let mut cursor = from.walk();
+        let image = from
+            .named_children(&mut cursor)
+            .find(|n| n.kind() == "image_spec" || n.kind() == "string")
+            .map(|n| doc.node_content(n));
+        image.is_some_and(|image| image != "scratch")
+    })
+}
+
+fn ancestor<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
+    let mut node = node;
+    while let Some(parent) = node.parent() {
+        if parent.kind() == kind {
+            return Some(parent);
+        }
+        node = parent;
+    }
+    None
+}
+
+fn from_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| Query::new(&crate::parser::language(), "(from_command) @from").unwrap())
+}
+
+fn shell_assignment_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(
+            &crate::bash_parser::language(),
+            r#"(variable_assignment name: (variable_name) @name)
+              (for_statement variable: (variable_name) @name)
+              (command
+                name: (command_name (word) @command)
+                argument: (word) @name
+                (#eq? @command "read"))"#,
+        )
+        .unwrap()
+    })
+}
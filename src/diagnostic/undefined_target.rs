@@ -0,0 +1,34 @@
+use tower_lsp::{jsonrpc::Result, lsp_types::*};
+
+use crate::{
+    document::Document,
+    reference::{references, ResolvedReferences},
+    util::ToLSPRange,
+};
+
+pub fn undefined_target(
+    resolved: &ResolvedReferences,
+    uri: &Url,
+    doc: &Document,
+) -> Result<Vec<Diagnostic>> {
+    let mut res = Vec::new();
+    for r in references(doc) {
+        let matches = resolved.matches(uri, &r);
+        // we can't say anything about the earthfiles that are not known
+        if matches.is_empty() {
+            continue;
+        }
+        if !matches.iter().any(|m| m.declares) {
+            let kind = if r.is_function() { "function" } else { "target" };
+            res.push(Diagnostic {
+                range: r.range.to_lsp_range(),
+                message: format!("undefined {kind} +{}", r.name),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some(super::SOURCE.to_string()),
+                code: super::code("undefined_target"),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(res)
+}
@@ -3,18 +3,35 @@ use std::sync::OnceLock;
 use tower_lsp::{jsonrpc::Result, lsp_types::*};
 use tree_sitter::Query;
 
+use super::QuickFix;
 use crate::{document::Document, util::ToLSPRange};
 
 pub fn unknown_option(doc: &Document) -> Result<Vec<Diagnostic>> {
     Ok(doc
         .captures(unknown_option_query())
         .iter()
-        .map(|node| Diagnostic {
-            range: node.range().to_lsp_range(),
-            message: "unknown option".to_string(),
-            severity: Some(DiagnosticSeverity::ERROR),
-            source: Some(super::SOURCE.to_string()),
-            ..Default::default()
+        .map(|node| {
+            // also remove the spaces up to the next element of the command
+            let mut range = node.range().to_lsp_range();
+            if let Some(next) = node
+                .next_sibling()
+                .or_else(|| node.parent().and_then(|options| options.next_sibling()))
+                .map(|next| next.start_position())
+                .filter(|next| next.row == node.end_position().row)
+            {
+                range.end.character = next.column as u32;
+            }
+            let edit = TextEdit { range, new_text: String::new() };
+            Diagnostic {
+                range: node.range().to_lsp_range(),
+                message: "unknown option".to_string(),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some(super::SOURCE.to_string()),
+                code: super::code("unknown_option"),
+                data: QuickFix::new(format!("Remove {}", doc.node_content(*node)), vec![edit])
+                    .to_data(),
+                ..Default::default()
+            }
         })
         .collect())
 }
@@ -6,6 +6,7 @@ use tree_sitter::{InputEdit, Node, Point, Query, QueryCursor, Tree};
 
 use crate::util::RopeProvider;
 
+#[derive(Clone)]
 pub struct Document {
     pub rope: Rope,
     pub tree: Tree,
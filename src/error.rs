@@ -20,6 +20,18 @@ pub enum EarthlylsError {
 
     #[error("Can't convert path {path} to URL")]
     PathToUrl { path: PathBuf },
+
+    #[error("{path}: can't compute the diagnostics: {message}")]
+    Diagnostic { path: PathBuf, message: String },
+
+    #[error("{path}: invalid configuration: {message}")]
+    Config { path: PathBuf, message: String },
+
+    #[error("can't compute the target graph: {message}")]
+    Graph { message: String },
+
+    #[error("can't write the output: {source}")]
+    Output { source: io::Error },
 }
 
 impl From<EarthlylsError> for tower_lsp::jsonrpc::Error {
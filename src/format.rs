@@ -0,0 +1,224 @@
+use tree_sitter::Node;
+
+use crate::document::Document;
+
+/// The nodes whose children are formatted one by one. The other nodes are kept as is, so the strings and the shell
+/// fragments are never modified.
+const CONTAINERS: [&str; 5] = ["options", "build_args", "strings", "elif_block", "else_block"];
+
+/// The indentation of a level: some spaces, or a tab
+pub fn indent(tab_size: u32, insert_spaces: bool) -> String {
+    if insert_spaces {
+        " ".repeat(tab_size as usize)
+    } else {
+        "\t".to_string()
+    }
+}
+
+/// Pretty-print an Earthfile. Returns `None` when the document has syntax errors, because there is no safe way to
+/// format it.
+pub fn format(doc: &Document, indent: &str) -> Option<String> {
+    if doc.tree.root_node().has_error() {
+        return None;
+    }
+    let units = units(doc);
+    let mut formatter = Formatter::new(doc, indent);
+    for (i, unit) in units.iter().enumerate() {
+        if i > 0 {
+            let prev = units[i - 1];
+            let blank = formatter.has_blank_line_between(prev, *unit);
+            let is_target = unit.kind() == "target";
+            let follows_comment = prev.kind() == "comment" && !blank;
+            // a blank line before a target, or before the comments documenting it
+            let starts_target_doc = unit.kind() == "comment"
+                && !follows_comment
+                && units[i..]
+                    .windows(2)
+                    .take_while(|w| {
+                        w[0].kind() == "comment" && !formatter.has_blank_line_between(w[0], w[1])
+                    })
+                    .last()
+                    .is_some_and(|w| w[1].kind() == "target");
+            if blank || (is_target && !follows_comment) || starts_target_doc {
+                formatter.out.push('\n');
+            }
+        }
+        formatter.unit(*unit);
+    }
+    Some(formatter.out)
+}
+
+/// The top level elements of the document — the VERSION command, the base target commands, the targets and the
+/// comments — along with their formatted text
+pub fn format_units<'doc>(doc: &'doc Document, indent: &str) -> Option<Vec<(Node<'doc>, String)>> {
+    if doc.tree.root_node().has_error() {
+        return None;
+    }
+    Some(
+        units(doc)
+            .into_iter()
+            .map(|unit| {
+                let mut formatter = Formatter::new(doc, indent);
+                formatter.unit(unit);
+                (unit, formatter.out)
+            })
+            .collect(),
+    )
+}
+
+/// The row of the last non whitespace character of a node
+pub fn content_end_row(doc: &Document, node: Node) -> usize {
+    let text = doc.node_content(node);
+    let len = text.trim_end().len();
+    if len == 0 {
+        node.start_position().row
+    } else {
+        doc.rope.byte_to_line(node.start_byte() + len - 1)
+    }
+}
+
+fn units(doc: &Document) -> Vec<Node<'_>> {
+    let root = doc.tree.root_node();
+    let mut res = Vec::new();
+    let mut cursor = root.walk();
+    for child in root.named_children(&mut cursor) {
+        if child.kind() == "block" {
+            let mut cursor = child.walk();
+            res.extend(child.named_children(&mut cursor));
+        } else {
+            res.push(child);
+        }
+    }
+    res
+}
+
+struct Formatter<'a> {
+    doc: &'a Document,
+    indent: &'a str,
+    out: String,
+    /// the indentation level to use for the next element when it starts a new line
+    line_start: Option<usize>,
+    /// the end of the last element written, to know if the next one was separated by some spaces
+    prev_end: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(doc: &'a Document, indent: &'a str) -> Self {
+        Formatter { doc, indent, out: String::new(), line_start: Some(0), prev_end: 0 }
+    }
+
+    fn unit(&mut self, node: Node) {
+        if node.kind() == "comment" {
+            self.comment_line(node, 0);
+        } else {
+            self.command(node, 0);
+        }
+    }
+
+    fn has_blank_line_between(&self, prev: Node, next: Node) -> bool {
+        next.start_position().row > content_end_row(self.doc, prev) + 1
+    }
+
+    fn comment_line(&mut self, node: Node, depth: usize) {
+        self.push_indent(depth);
+        self.out.push_str(self.doc.node_content(node).trim());
+        self.out.push('\n');
+        self.line_start = Some(depth);
+    }
+
+    /// A command, or a target, with the blocks it contains
+    fn command(&mut self, node: Node, depth: usize) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.line_start = Some(depth);
+        self.children(node, depth);
+        if !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.line_start = Some(depth);
+    }
+
+    fn children(&mut self, node: Node, depth: usize) {
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.is_missing() || child.start_byte() == child.end_byte() {
+                continue;
+            }
+            match child.kind() {
+                "\n" | "\r\n" | "\x0c" | "\0" if !child.is_named() => {
+                    if !self.out.ends_with('\n') {
+                        self.out.push('\n');
+                    }
+                    self.line_start = Some(depth);
+                }
+                "line_continuation" => {
+                    self.out.push_str(" \\\n");
+                    self.line_start = Some(depth + 1);
+                }
+                "line_continuation_comment" => {
+                    self.out.push(' ');
+                    self.out.push_str(self.doc.node_content(child).trim_end());
+                    self.out.push('\n');
+                    self.line_start = Some(depth + 1);
+                }
+                "comment" => {
+                    match self.line_start {
+                        Some(depth) => self.push_indent(depth),
+                        None => self.out.push(' '),
+                    }
+                    self.out.push_str(self.doc.node_content(child).trim());
+                    self.out.push('\n');
+                    self.line_start = Some(depth);
+                }
+                "block" => {
+                    self.block(child, depth + 1);
+                    self.line_start = Some(depth);
+                }
+                // the RUN command of a WITH DOCKER block
+                kind if kind.ends_with("_command") => {
+                    self.command(child, depth + 1);
+                    self.line_start = Some(depth);
+                }
+                kind if CONTAINERS.contains(&kind) => self.children(child, depth),
+                _ => self.atom(child),
+            }
+        }
+    }
+
+    fn block(&mut self, node: Node, depth: usize) {
+        if !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        let mut prev: Option<Node> = None;
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if prev.is_some_and(|prev| self.has_blank_line_between(prev, child)) {
+                self.out.push('\n');
+            }
+            if child.kind() == "comment" {
+                self.comment_line(child, depth);
+            } else {
+                self.command(child, depth);
+            }
+            prev = Some(child);
+        }
+    }
+
+    /// An element written as is, separated from the previous one by a single space, if it was separated at all
+    fn atom(&mut self, node: Node) {
+        match self.line_start.take() {
+            Some(depth) => self.push_indent(depth),
+            None if node.start_byte() > self.prev_end => self.out.push(' '),
+            None => (),
+        }
+        self.out.push_str(&self.doc.node_content(node));
+        self.prev_end = node.end_byte();
+    }
+
+    fn push_indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str(self.indent);
+        }
+    }
+}
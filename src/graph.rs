@@ -0,0 +1,244 @@
+use std::{collections::HashMap, fmt::Write};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tower_lsp::{
+    jsonrpc::Result,
+    lsp_types::{Range, Url},
+};
+
+use crate::{
+    backend::Backend,
+    document::Document,
+    import::enclosing_target,
+    reference::{is_function_target, references, target_names, ResolvedReferences},
+    util::ToLSPRange,
+};
+
+/// The formats of the target graph
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    #[default]
+    Json,
+}
+
+/// A target or a function, identified by its workspace relative directory and its name, like `services/api+image`, or
+/// by its remote reference for the remote Earthfiles, like `github.com/earthly/lib+INSTALL_DIND`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GraphNode {
+    pub id: String,
+    pub earthfile: String,
+    pub name: String,
+    pub function: bool,
+}
+
+/// The command referencing a target
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EdgeKind {
+    Build,
+    From,
+    FromDockerfile,
+    Copy,
+    Do,
+    WithDocker,
+    Other,
+}
+
+impl EdgeKind {
+    fn of_command(kind: &str) -> Self {
+        match kind {
+            "build_command" => EdgeKind::Build,
+            "from_command" => EdgeKind::From,
+            "from_dockerfile_command" => EdgeKind::FromDockerfile,
+            "copy_command" => EdgeKind::Copy,
+            "do_command" => EdgeKind::Do,
+            "with_docker_command" => EdgeKind::WithDocker,
+            _ => EdgeKind::Other,
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            EdgeKind::Build => "BUILD",
+            EdgeKind::From => "FROM",
+            EdgeKind::FromDockerfile => "FROM DOCKERFILE",
+            EdgeKind::Copy => "COPY",
+            EdgeKind::Do => "DO",
+            EdgeKind::WithDocker => "WITH DOCKER",
+            EdgeKind::Other => "OTHER",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// The dependencies between the targets of all the loaded Earthfiles
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A reference from a target of a document to a target of a known Earthfile
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetEdge {
+    /// the name of the target making the reference
+    pub from: String,
+    pub to_uri: Url,
+    pub to: String,
+    pub kind: EdgeKind,
+    /// the range of the reference
+    pub range: Range,
+}
+
+/// The references made by the targets of a document, to the targets declared in the known Earthfiles. The references
+/// made in the base target are not part of it, because they are not made by a target.
+pub fn target_edges(resolved: &ResolvedReferences, uri: &Url, doc: &Document) -> Vec<TargetEdge> {
+    let mut res = Vec::new();
+    for reference in references(doc) {
+        let Some(from) = enclosing_target(reference.node)
+            .and_then(|t| t.child_by_field_name("name"))
+            .map(|n| doc.node_content(n))
+        else {
+            continue;
+        };
+        let mut command = reference.node;
+        while !command.kind().ends_with("_command") {
+            let Some(parent) = command.parent() else {
+                break;
+            };
+            command = parent;
+        }
+        let kind = EdgeKind::of_command(command.kind());
+        for m in resolved.matches(uri, &reference).iter().filter(|m| m.declares) {
+            res.push(TargetEdge {
+                from: from.clone(),
+                to_uri: m.uri.to_owned(),
+                to: reference.name.clone(),
+                kind,
+                range: reference.range.to_lsp_range(),
+            });
+        }
+    }
+    res
+}
+
+/// Build the target graph of all the known Earthfiles
+pub fn target_graph(backend: &Backend) -> Result<Graph> {
+    let resolved = ResolvedReferences::new(backend)?;
+    let mut graph = Graph::default();
+    for item in backend.docs.iter() {
+        let uri = item.key();
+        let doc = item.value();
+        let dir = earthfile_dir(backend, uri);
+        for name in target_names(doc) {
+            let Some(target) = name.parent() else {
+                continue;
+            };
+            let name = doc.node_content(name);
+            graph.nodes.push(GraphNode {
+                id: node_id(&dir, &name),
+                earthfile: earthfile_path(backend, uri),
+                name,
+                function: is_function_target(target),
+            });
+        }
+        for edge in target_edges(&resolved, uri, doc) {
+            graph.edges.push(GraphEdge {
+                from: node_id(&dir, &edge.from),
+                to: node_id(&earthfile_dir(backend, &edge.to_uri), &edge.to),
+                kind: edge.kind,
+            });
+        }
+    }
+    graph.nodes.sort();
+    graph.nodes.dedup();
+    graph.edges.sort();
+    graph.edges.dedup();
+    Ok(graph)
+}
+
+impl Graph {
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+            GraphFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut res = "digraph earthly {\n".to_string();
+        for node in &self.nodes {
+            let shape = if node.function { "ellipse" } else { "box" };
+            writeln!(res, "    \"{}\" [shape={shape}];", node.id).unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                res,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                edge.from,
+                edge.to,
+                edge.kind.keyword()
+            )
+            .unwrap();
+        }
+        res.push_str("}\n");
+        res
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut res = "flowchart LR\n".to_string();
+        // mermaid ids can't contain the characters used in the target ids
+        let index: HashMap<&str, usize> =
+            self.nodes.iter().enumerate().map(|(i, node)| (node.id.as_str(), i)).collect();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.function {
+                writeln!(res, "    n{i}([\"{}\"])", node.id).unwrap();
+            } else {
+                writeln!(res, "    n{i}[\"{}\"]", node.id).unwrap();
+            }
+        }
+        for edge in &self.edges {
+            // an edge always links two nodes of the graph, but a malformed one must not be drawn to another node
+            let (Some(from), Some(to)) =
+                (index.get(edge.from.as_str()), index.get(edge.to.as_str()))
+            else {
+                continue;
+            };
+            writeln!(res, "    n{from} -->|{}| n{to}", edge.kind.keyword()).unwrap();
+        }
+        res
+    }
+}
+
+/// The id of a target in the graph, like `services/api+image`, or `+build` for the workspace root
+pub fn node_id(dir: &str, name: &str) -> String {
+    if dir == "." {
+        format!("+{name}")
+    } else {
+        format!("{dir}+{name}")
+    }
+}
+
+/// The path of an Earthfile in the graph: relative to its workspace, or prefixed with its remote reference
+pub fn earthfile_path(backend: &Backend, uri: &Url) -> String {
+    backend.remote_path(uri).unwrap_or_else(|| backend.workspace_path(uri))
+}
+
+/// The directory of an Earthfile in the node ids, `.` for the workspace root
+pub fn earthfile_dir(backend: &Backend, uri: &Url) -> String {
+    match earthfile_path(backend, uri).rsplit_once('/') {
+        Some((dir, _)) => dir.to_string(),
+        None => ".".to_string(),
+    }
+}
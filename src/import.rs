@@ -0,0 +1,139 @@
+use std::sync::OnceLock;
+
+use tree_sitter::{Node, Query};
+
+use crate::document::Document;
+
+/// An IMPORT command, with its alias already inferred when there is no explicit `AS <alias>`
+#[derive(Debug, Clone)]
+pub struct Import<'doc> {
+    pub node: Node<'doc>,
+    pub alias: String,
+    /// the node of the explicit alias, if any
+    pub alias_node: Option<Node<'doc>>,
+    pub earthfile: String,
+    pub earthfile_node: Node<'doc>,
+}
+
+/// All the IMPORT commands of a document, global or not
+pub fn imports(doc: &Document) -> Vec<Import<'_>> {
+    doc.captures(import_query()).into_iter().filter_map(|node| import(doc, node)).collect()
+}
+
+/// The IMPORT commands visible from the given node: the global ones, from the base target, and the ones from the
+/// target containing the node. The target IMPORTs come first so they take precedence over the global ones.
+pub fn visible_imports<'doc>(doc: &'doc Document, node: Node<'doc>) -> Vec<Import<'doc>> {
+    let target = enclosing_target(node);
+    let mut res: Vec<_> =
+        imports(doc).into_iter().filter(|i| enclosing_target(i.node) == target).collect();
+    if target.is_some() {
+        res.extend(imports(doc).into_iter().filter(|i| enclosing_target(i.node).is_none()));
+    }
+    res
+}
+
+/// Find the earthfile reference imported with the given alias, as seen from the given node
+pub fn resolve_alias(doc: &Document, node: Node, alias: &str) -> Option<String> {
+    visible_imports(doc, node).into_iter().find(|i| i.alias == alias).map(|i| i.earthfile)
+}
+
+/// Replace an IMPORT alias with the earthfile reference it stands for. The earthfile reference is returned unchanged
+/// if it is not an alias.
+pub fn resolve_earthfile_ref(doc: &Document, node: Node, earthfile_ref: &str) -> String {
+    if is_alias(earthfile_ref) {
+        resolve_alias(doc, node, earthfile_ref).unwrap_or_else(|| earthfile_ref.to_owned())
+    } else {
+        earthfile_ref.to_owned()
+    }
+}
+
+/// An alias is a simple name, while the other earthfile references are paths
+pub fn is_alias(earthfile_ref: &str) -> bool {
+    !earthfile_ref.is_empty()
+        && earthfile_ref != "."
+        && earthfile_ref != ".."
+        && earthfile_ref.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// A remote earthfile reference, like `github.com/earthly/lib:3.0.1`, starts with a host name
+pub fn is_remote(earthfile_ref: &str) -> bool {
+    earthfile_ref
+        .split_once('/')
+        .is_some_and(|(host, _)| host.contains('.') && !host.starts_with('.'))
+}
+
+/// Split a remote earthfile reference in its path and its git reference — a tag, a branch or a commit
+pub fn split_git_ref(earthfile_ref: &str) -> (&str, Option<&str>) {
+    match earthfile_ref.split_once(':') {
+        Some((path, git_ref)) => (path, Some(git_ref)),
+        None => (earthfile_ref, None),
+    }
+}
+
+/// The alias inferred by earthly when none is provided: the last element of the path, without the git reference
+pub fn infer_alias(earthfile_ref: &str) -> Option<String> {
+    let path = earthfile_ref.trim_end_matches('/');
+    let name = path.rsplit('/').next()?;
+    let name = name.split(':').next()?;
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+fn import<'doc>(doc: &'doc Document, node: Node<'doc>) -> Option<Import<'doc>> {
+    let mut cursor = node.walk();
+    let earthfile_node = node
+        .named_children(&mut cursor)
+        .find(|n| ["earthfile_ref", "string"].contains(&n.grammar_name()))?;
+    let earthfile = doc.node_content(earthfile_node);
+    let alias_node = node.child_by_field_name("alias");
+    let alias = match alias_node {
+        Some(alias_node) => doc.node_content(alias_node),
+        None => infer_alias(&earthfile)?,
+    };
+    Some(Import { node, alias, alias_node, earthfile, earthfile_node })
+}
+
+pub fn enclosing_target(node: Node) -> Option<Node> {
+    let mut node = node;
+    while let Some(parent) = node.parent() {
+        if parent.grammar_name() == "target" {
+            return Some(parent);
+        }
+        node = parent;
+    }
+    None
+}
+
+fn import_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(&crate::parser::language(), r"(import_command) @import_command").unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_alias, is_alias};
+
+    #[test]
+    fn should_infer_alias() {
+        assert_eq!(infer_alias("./lib/go"), Some("go".to_string()));
+        assert_eq!(infer_alias("github.com/foo/bar/buz:v1.2.3"), Some("buz".to_string()));
+        assert_eq!(infer_alias("../lib/"), Some("lib".to_string()));
+        assert_eq!(infer_alias(".."), None);
+        assert_eq!(infer_alias("../.."), None);
+    }
+
+    #[test]
+    fn should_recognize_alias() {
+        assert!(is_alias("golib"));
+        assert!(is_alias("go-lib"));
+        assert!(!is_alias("./golib"));
+        assert!(!is_alias(".."));
+        assert!(!is_alias("github.com/earthly/lib"));
+        assert!(!is_alias("./*"));
+    }
+}
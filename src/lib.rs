@@ -1,10 +1,17 @@
 pub mod backend;
 pub mod bash_parser;
+pub mod call;
 pub mod cli;
 pub mod commands;
 pub mod descriptions;
 pub mod diagnostic;
 pub mod document;
 pub mod error;
+pub mod format;
+pub mod graph;
+pub mod import;
 pub mod parser;
+pub mod reference;
+pub mod settings;
 pub mod util;
+pub mod variable;
@@ -1,16 +1,37 @@
 use clap::{CommandFactory, Parser};
-use earthlyls::{backend::Backend, cli};
-use tower_lsp::{LspService, Server};
+use earthlyls::{
+    backend,
+    cli::{self, Command},
+    format,
+};
+use tower_lsp::Server;
 
 #[tokio::main]
 async fn main() {
-    cli::Cli::parse();
+    let args = cli::Cli::parse();
+
+    if let Some(command) = args.command {
+        let res = match command {
+            Command::Check { format, dir } => cli::check::check(&dir, format),
+            Command::Fmt { check, tab_size, tabs, paths } => {
+                cli::fmt::fmt(check, &format::indent(tab_size, !tabs), &paths)
+            }
+            Command::Graph { format, dir } => cli::graph::graph(&dir, format),
+        };
+        match res {
+            Ok(true) => std::process::exit(0),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(2);
+            }
+        }
+    }
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| {
-        Backend::new(client, cli::Cli::command().get_version().unwrap().to_string())
-    });
+    let (service, socket) =
+        backend::service(cli::Cli::command().get_version().unwrap().to_string());
     Server::new(stdin, stdout, socket).serve(service).await;
 }
@@ -0,0 +1,235 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use tower_lsp::{jsonrpc::Result, lsp_types::Url};
+use tree_sitter::{Node, Point, Query, QueryCursor};
+
+use crate::{backend::Backend, document::Document, import, util::RopeProvider};
+
+/// A reference to a target or a function
+///
+/// The references using an IMPORT alias, like `golib+test`, are not recognized by the grammar, which parses them as
+/// plain strings. They are extracted from those strings when the alias matches a visible IMPORT.
+#[derive(Debug, Clone)]
+pub struct Reference<'doc> {
+    /// the `target_ref`, `function_ref` or `string` node containing the reference
+    pub node: Node<'doc>,
+    /// the range of the reference, without the artifact path
+    pub range: tree_sitter::Range,
+    /// the earthfile part of the reference, as written in the document
+    pub earthfile_ref: Option<String>,
+    pub earthfile_range: Option<tree_sitter::Range>,
+    /// the earthfile part of the reference, with the IMPORT alias replaced by the imported earthfile reference
+    pub earthfile: Option<String>,
+    pub name: String,
+    pub name_range: tree_sitter::Range,
+}
+
+impl<'doc> Reference<'doc> {
+    fn from_ref_node(doc: &'doc Document, node: Node<'doc>) -> Option<Self> {
+        let name_node = node.child_by_field_name("name")?;
+        let earthfile_node = node.child_by_field_name("earthfile");
+        let earthfile_ref = earthfile_node.map(|n| doc.node_content(n));
+        let earthfile = earthfile_ref.as_ref().map(|e| import::resolve_earthfile_ref(doc, node, e));
+        Some(Reference {
+            node,
+            range: node.range(),
+            earthfile_ref,
+            earthfile_range: earthfile_node.map(|n| n.range()),
+            earthfile,
+            name: doc.node_content(name_node),
+            name_range: name_node.range(),
+        })
+    }
+
+    fn from_string_node(doc: &'doc Document, node: Node<'doc>) -> Option<Self> {
+        // keep it simple and only consider the strings on a single line
+        if node.start_position().row != node.end_position().row {
+            return None;
+        }
+        let content = doc.node_content(node);
+        let (alias, rest) = content.split_once('+')?;
+        if !import::is_alias(alias) {
+            return None;
+        }
+        let earthfile = import::resolve_alias(doc, node, alias)?;
+        let name_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.'))
+            .unwrap_or(rest.len());
+        if name_len == 0 {
+            return None;
+        }
+        let name_start = alias.len() + 1;
+        let name_end = name_start + name_len;
+        Some(Reference {
+            node,
+            range: sub_range(node, 0, name_end),
+            earthfile_ref: Some(alias.to_owned()),
+            earthfile_range: Some(sub_range(node, 0, alias.len())),
+            earthfile: Some(earthfile),
+            name: rest[..name_len].to_owned(),
+            name_range: sub_range(node, name_start, name_end),
+        })
+    }
+
+    /// Whether the reference is a function reference, used in a DO command
+    pub fn is_function(&self) -> bool {
+        self.node.kind() == "function_ref"
+            || self.node.parent().map(|p| p.kind()) == Some("do_command")
+    }
+}
+
+/// A known Earthfile matched by a reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EarthfileMatch {
+    pub uri: Url,
+    /// whether the Earthfile declares the referenced target
+    pub declares: bool,
+}
+
+/// The Earthfiles matched by the references of all the known documents. The references are matched once, and the
+/// result is shared by the diagnostics of all the documents.
+#[derive(Debug, Default)]
+pub struct ResolvedReferences {
+    /// the matches of each reference, by document and by start byte of the reference
+    matches: HashMap<Url, HashMap<usize, Vec<EarthfileMatch>>>,
+}
+
+impl ResolvedReferences {
+    pub fn new(backend: &Backend) -> Result<Self> {
+        let targets: HashMap<Url, HashSet<String>> = backend
+            .docs
+            .iter()
+            .map(|item| {
+                let doc = item.value();
+                let names = target_names(doc).into_iter().map(|n| doc.node_content(n)).collect();
+                (item.key().to_owned(), names)
+            })
+            .collect();
+        // many references share the same earthfile, so the Earthfiles are matched once for each directory
+        let mut earthfiles: HashMap<PathBuf, Vec<Url>> = HashMap::new();
+        let mut matches = HashMap::new();
+        for item in backend.docs.iter() {
+            let uri = item.key();
+            let mut doc_matches = HashMap::new();
+            for r in references(item.value()) {
+                let uris = match &r.earthfile {
+                    None => vec![uri.to_owned()],
+                    Some(earthfile) => match backend.earthfile_ref_dir(uri, earthfile)? {
+                        Some(dir) => earthfiles
+                            .entry(dir)
+                            .or_insert_with_key(|dir| backend.match_earthfile_dir(dir))
+                            .clone(),
+                        None => Vec::new(),
+                    },
+                };
+                let earthfile_matches = uris
+                    .into_iter()
+                    .map(|uri| {
+                        let declares = targets.get(&uri).is_some_and(|t| t.contains(&r.name));
+                        EarthfileMatch { uri, declares }
+                    })
+                    .collect();
+                doc_matches.insert(r.range.start_byte, earthfile_matches);
+            }
+            matches.insert(uri.to_owned(), doc_matches);
+        }
+        Ok(ResolvedReferences { matches })
+    }
+
+    /// The Earthfiles matched by a reference of a document
+    pub fn matches(&self, uri: &Url, reference: &Reference) -> &[EarthfileMatch] {
+        self.matches
+            .get(uri)
+            .and_then(|m| m.get(&reference.range.start_byte))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// All the target and function references in a document
+pub fn references(doc: &Document) -> Vec<Reference<'_>> {
+    let query = reference_query();
+    let ref_idx = query.capture_index_for_name("ref").unwrap();
+    let string_idx = query.capture_index_for_name("string").unwrap();
+    let mut query_cursor = QueryCursor::new();
+    let mut res = Vec::new();
+    for m in query_cursor.matches(query, doc.tree.root_node(), RopeProvider(doc.rope.slice(..))) {
+        for c in m.captures {
+            let r = if c.index == ref_idx {
+                Reference::from_ref_node(doc, c.node)
+            } else if c.index == string_idx {
+                Reference::from_string_node(doc, c.node)
+            } else {
+                None
+            };
+            res.extend(r);
+        }
+    }
+    res
+}
+
+/// The target or function reference at the given position
+pub fn reference_at(doc: &Document, pos: Point) -> Option<Reference<'_>> {
+    references(doc).into_iter().find(|r| r.range.start_point <= pos && pos <= r.range.end_point)
+}
+
+/// The target name nodes of a document
+pub fn target_names(doc: &Document) -> Vec<Node<'_>> {
+    doc.captures(target_name_query())
+}
+
+/// A function is a target declared with the FUNCTION command
+pub fn is_function_target(target: Node) -> bool {
+    let Some(block) = target.named_children(&mut target.walk()).find(|n| n.kind() == "block")
+    else {
+        return false;
+    };
+    let mut cursor = block.walk();
+    let res = block.named_children(&mut cursor).any(|n| n.kind() == "function_command");
+    res
+}
+
+/// The target name node at the given position
+pub fn target_name_at(doc: &Document, pos: Point) -> Option<Node<'_>> {
+    target_names(doc).into_iter().find(|n| n.start_position() <= pos && pos <= n.end_position())
+}
+
+/// Build a range for a part of a single line node
+fn sub_range(node: Node, start: usize, end: usize) -> tree_sitter::Range {
+    let r = node.range();
+    tree_sitter::Range {
+        start_byte: r.start_byte + start,
+        end_byte: r.start_byte + end,
+        start_point: Point { row: r.start_point.row, column: r.start_point.column + start },
+        end_point: Point { row: r.start_point.row, column: r.start_point.column + end },
+    }
+}
+
+fn reference_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(
+            &crate::parser::language(),
+            r"(target_ref) @ref
+              (function_ref) @ref
+              (build_command (string) @string)
+              (from_command (string) @string)
+              (do_command (string) @string)
+              (copy_command src: (string) @string)
+              (target_artifact_build_args (string) @string)
+              ",
+        )
+        .unwrap()
+    })
+}
+
+fn target_name_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(&crate::parser::language(), r"(target name: (identifier) @target_name)").unwrap()
+    })
+}
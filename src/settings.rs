@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{DiagnosticSeverity, MessageType, Url};
+
+use crate::error::{self, EarthlylsError, IOResultExt};
+
+/// The name of the project configuration file, at the root of a workspace
+pub const PROJECT_CONFIG_FILE: &str = ".earthlyls.toml";
+
+/// The shared project manifest, where the configuration may be in a `[tool.earthlyls]` section instead
+pub const PROJECT_MANIFEST_FILE: &str = "pyproject.toml";
+
+/// The settings of the language server, set with the `initializationOptions` of the `initialize` request and updated
+/// with the `workspace/didChangeConfiguration` notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// the diagnostic settings, by rule name, like `undeclared_variable`
+    pub diagnostics: HashMap<String, RuleSettings>,
+    /// more glob patterns of files to load as Earthfiles, relative to the workspace roots
+    pub include: Vec<String>,
+    /// the glob patterns of the Earthfiles to ignore, relative to the workspace roots
+    pub exclude: Vec<String>,
+    /// the `VERSION` inserted by the missing-version quick fix
+    pub earthly_version: String,
+    pub log_level: LogLevel,
+    /// the local checkouts of the remote earthfile references, by repository prefix, like `github.com/earthly/lib`
+    pub remote_earthfiles: HashMap<String, PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            diagnostics: Default::default(),
+            include: Default::default(),
+            exclude: Default::default(),
+            earthly_version: "0.8".to_string(),
+            log_level: Default::default(),
+            remote_earthfiles: Default::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings from the client configuration. They may be in an `earthlyls` section.
+    pub fn from_value(value: serde_json::Value) -> serde_json::Result<Self> {
+        match value {
+            serde_json::Value::Null => Ok(Settings::default()),
+            serde_json::Value::Object(mut map) if map.contains_key("earthlyls") => {
+                serde_json::from_value(map.remove("earthlyls").unwrap())
+            }
+            value => serde_json::from_value(value),
+        }
+    }
+
+    /// Update the settings with a configuration change. Only the settings present in the change are replaced: the
+    /// clients may send an empty configuration, or no configuration at all, when nothing is configured on their side.
+    pub fn merge_value(&self, value: serde_json::Value) -> serde_json::Result<Self> {
+        let value = match value {
+            serde_json::Value::Object(mut map) if map.contains_key("earthlyls") => {
+                map.remove("earthlyls").unwrap()
+            }
+            value => value,
+        };
+        match value {
+            serde_json::Value::Null => Ok(self.clone()),
+            serde_json::Value::Object(changes) => {
+                let serde_json::Value::Object(mut current) = serde_json::to_value(self)? else {
+                    unreachable!("the settings are serialized as an object");
+                };
+                current.extend(changes);
+                serde_json::from_value(serde_json::Value::Object(current))
+            }
+            value => serde_json::from_value(value),
+        }
+    }
+
+    /// The settings of a diagnostic rule. The rule may be written with dashes, like `undefined-target`.
+    pub fn rule(&self, rule: &str) -> RuleSettings {
+        let rule = rule_name(rule);
+        self.diagnostics
+            .iter()
+            .find(|(name, _)| rule_name(name) == rule)
+            .map(|(_, settings)| settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Complete the settings with the ones of a project. The settings of the client take precedence, field by field,
+    /// so a user can still override a part of a rule configured for the whole project.
+    pub fn with_project(mut self, project: &ProjectSettings) -> Self {
+        for (name, rule) in &project.diagnostics {
+            match self.diagnostics.iter_mut().find(|(n, _)| rule_name(n) == rule_name(name)) {
+                Some((_, client_rule)) => {
+                    client_rule.enabled = client_rule.enabled.or(rule.enabled);
+                    client_rule.severity = client_rule.severity.or(rule.severity);
+                }
+                None => {
+                    self.diagnostics.insert(name.to_owned(), rule.to_owned());
+                }
+            }
+        }
+        self
+    }
+}
+
+/// The settings shared by all the users of a project, read from the `.earthlyls.toml` file at the root of the
+/// workspace, or from the `[tool.earthlyls]` section of its `pyproject.toml` manifest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// the diagnostic settings, by rule name
+    pub diagnostics: HashMap<String, RuleSettings>,
+    /// the glob patterns of the Earthfiles to ignore, relative to the workspace root
+    pub exclude: Vec<String>,
+}
+
+impl ProjectSettings {
+    /// Read the project settings of a directory, if it has a configuration file or a manifest with an `earthlyls`
+    /// section. The configuration file takes precedence.
+    pub fn load(dir: &Path) -> error::Result<Option<Self>> {
+        let read = |path: &Path| std::fs::read_to_string(path).path_ctx(path);
+        let config_error = |path: PathBuf, e: toml::de::Error| EarthlylsError::Config {
+            path,
+            message: e.to_string(),
+        };
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        if path.exists() {
+            return Self::from_toml(&read(&path)?).map(Some).map_err(|e| config_error(path, e));
+        }
+        let path = dir.join(PROJECT_MANIFEST_FILE);
+        if path.exists() {
+            return Self::from_manifest(&read(&path)?).map_err(|e| config_error(path, e));
+        }
+        Ok(None)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Read the `[tool.earthlyls]` section of a project manifest, if any
+    pub fn from_manifest(content: &str) -> Result<Option<Self>, toml::de::Error> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        let section = table
+            .get_mut("tool")
+            .and_then(|tool| tool.as_table_mut())
+            .and_then(|tool| tool.remove("earthlyls"));
+        section.map(|section| section.try_into()).transpose()
+    }
+
+    /// Whether a file may contain the project settings
+    pub fn is_config_file(uri: &Url) -> bool {
+        [PROJECT_CONFIG_FILE, PROJECT_MANIFEST_FILE]
+            .iter()
+            .any(|name| uri.path().ends_with(&format!("/{name}")))
+    }
+}
+
+/// The rule names are the diagnostic codes, with underscores, but they may also be written with dashes
+fn rule_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+/// The settings of a diagnostic rule. An unset field keeps the value of the project configuration, or the default one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuleSettings {
+    /// `true` by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// replaces the default severity of the rule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+impl RuleSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(val: Severity) -> Self {
+        match val {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Information => DiagnosticSeverity::INFORMATION,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+/// The minimum level of the messages logged to the client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warning,
+    #[default]
+    Info,
+}
+
+impl LogLevel {
+    pub fn logs(&self, typ: MessageType) -> bool {
+        let level = match typ {
+            MessageType::ERROR => LogLevel::Error,
+            MessageType::WARNING => LogLevel::Warning,
+            _ => LogLevel::Info,
+        };
+        level <= *self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProjectSettings, RuleSettings, Settings, Severity};
+
+    #[test]
+    fn should_read_project_settings() {
+        let project = ProjectSettings::from_toml(
+            r#"
+            exclude = ["vendor/**"]
+
+            [diagnostics]
+            undefined-target = { enabled = false }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(project.exclude, vec!["vendor/**".to_string()]);
+        assert!(!Settings::default().with_project(&project).rule("undefined_target").is_enabled());
+
+        let project = ProjectSettings::from_manifest(
+            r#"
+            [project]
+            name = "foo"
+
+            [tool.earthlyls.diagnostics]
+            undeclared_variable = { severity = "error" }
+            "#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            Settings::default().with_project(&project).rule("undeclared-variable").severity,
+            Some(Severity::Error)
+        );
+        assert_eq!(
+            ProjectSettings::from_manifest("[tool.black]\nline-length = 100\n").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn should_prefer_client_settings() {
+        let project =
+            ProjectSettings::from_toml("[diagnostics]\nmissing-version = { enabled = false }\n")
+                .unwrap();
+        let mut settings = Settings::default();
+        settings.diagnostics.insert(
+            "missing_version".to_string(),
+            RuleSettings { enabled: Some(true), severity: None },
+        );
+        assert!(settings.with_project(&project).rule("missing_version").is_enabled());
+    }
+
+    #[test]
+    fn should_merge_client_and_project_rule_settings() {
+        let project = ProjectSettings::from_toml(
+            "[diagnostics]
+missing-version = { enabled = false }
+",
+        )
+        .unwrap();
+        let settings = Settings::from_value(serde_json::json!({
+            "diagnostics": { "missing_version": { "severity": "hint" } },
+        }))
+        .unwrap();
+        let rule = settings.with_project(&project).rule("missing_version");
+        assert!(!rule.is_enabled());
+        assert_eq!(rule.severity, Some(Severity::Hint));
+    }
+}
@@ -1,11 +1,5 @@
-use clean_path::Clean;
-use glob_match::glob_match;
-use path_slash::PathBufExt;
 use ropey::RopeSlice;
-use tower_lsp::{
-    jsonrpc::{Error, Result},
-    lsp_types::{self, Url},
-};
+use tower_lsp::{jsonrpc::Error, lsp_types};
 use tree_sitter::{Node, TextProvider};
 
 /// Adapter to use a rope slice in tree-sitter queries
@@ -60,18 +54,3 @@ pub fn request_failed(msg: &str) -> Error {
         data: None,
     }
 }
-
-pub fn is_earthfile_ref_match(origin: &Url, earthfile_ref: &str, target_uri: &Url) -> Result<bool> {
-    let path =
-        origin.to_file_path().map_err(|_| request_failed("can't compute the earthfile path"))?;
-    let path = path
-        .parent()
-        .ok_or_else(|| request_failed("can't compute the current Earthfile parent"))?;
-    let path = path.join(earthfile_ref).join("Earthfile").clean().to_slash_lossy().to_string();
-    let target_path = target_uri
-        .to_file_path()
-        .map_err(|_| request_failed("can't compute the earthfile path"))?
-        .to_string_lossy()
-        .to_string();
-    Ok(glob_match(&path, &target_path))
-}
@@ -0,0 +1,365 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use tree_sitter::{Node, Point, Query, QueryCursor};
+
+use crate::{
+    document::Document,
+    import::enclosing_target,
+    reference::{references, target_names, Reference},
+    util::RopeProvider,
+};
+
+/// How deep to follow the FROM commands to find the inherited ENV variables
+pub const MAX_FROM_DEPTH: usize = 8;
+
+/// The args provided by earthly. They still have to be declared with ARG to be used in a target, but there is no
+/// point to report them as undeclared.
+pub const BUILTIN_ARGS: [&str; 37] = [
+    "EARTHLY_BUILD_SHA",
+    "EARTHLY_CI",
+    "EARTHLY_CI_RUNNER",
+    "EARTHLY_GIT_AUTHOR",
+    "EARTHLY_GIT_AUTHOR_EMAIL",
+    "EARTHLY_GIT_AUTHOR_NAME",
+    "EARTHLY_GIT_BRANCH",
+    "EARTHLY_GIT_CO_AUTHORS",
+    "EARTHLY_GIT_COMMIT_AUTHOR_TIMESTAMP",
+    "EARTHLY_GIT_COMMIT_TIMESTAMP",
+    "EARTHLY_GIT_HASH",
+    "EARTHLY_GIT_ORIGIN_URL",
+    "EARTHLY_GIT_PROJECT_NAME",
+    "EARTHLY_GIT_REFS",
+    "EARTHLY_GIT_SHORT_HASH",
+    "EARTHLY_LOCALLY",
+    "EARTHLY_PUSH",
+    "EARTHLY_SOURCE_DATE_EPOCH",
+    "EARTHLY_TARGET",
+    "EARTHLY_TARGET_NAME",
+    "EARTHLY_TARGET_PROJECT",
+    "EARTHLY_TARGET_PROJECT_NO_TAG",
+    "EARTHLY_TARGET_TAG",
+    "EARTHLY_TARGET_TAG_DOCKER",
+    "EARTHLY_VERSION",
+    "NATIVEARCH",
+    "NATIVEOS",
+    "NATIVEPLATFORM",
+    "NATIVEVARIANT",
+    "TARGETARCH",
+    "TARGETOS",
+    "TARGETPLATFORM",
+    "TARGETVARIANT",
+    "USERARCH",
+    "USEROS",
+    "USERPLATFORM",
+    "USERVARIANT",
+];
+
+/// A variable declared with ARG, LET, ENV or FOR
+#[derive(Debug, Clone)]
+pub struct Declaration<'doc> {
+    /// the declaring command: `arg_command`, `let_command`, `env_command` or `for_command`
+    pub command: Node<'doc>,
+    pub name_node: Node<'doc>,
+    pub name: String,
+}
+
+impl<'doc> Declaration<'doc> {
+    /// A global ARG is visible in all the targets of the Earthfile
+    pub fn is_global(&self) -> bool {
+        self.command.kind() == "arg_command" && has_option(self.command, "global")
+    }
+
+    /// A required ARG must be set by the caller
+    pub fn is_required(&self) -> bool {
+        self.command.kind() == "arg_command" && has_option(self.command, "required")
+    }
+
+    /// The target declaring the variable, or `None` for the base target
+    pub fn target(&self) -> Option<Node<'doc>> {
+        enclosing_target(self.command)
+    }
+
+    /// The command keyword: `ARG`, `LET`, `ENV` or `FOR`
+    pub fn keyword(&self) -> &'static str {
+        match self.command.kind() {
+            "arg_command" => "ARG",
+            "let_command" => "LET",
+            "env_command" => "ENV",
+            _ => "FOR",
+        }
+    }
+
+    /// The default value of an ARG, or the value of a LET or an ENV
+    pub fn value(&self) -> Option<Node<'doc>> {
+        self.command
+            .child_by_field_name("default_value")
+            .or_else(|| self.command.child_by_field_name("value"))
+    }
+}
+
+/// The usage of a variable, either in an expansion — in the Earthfile or in a shell fragment — or in a SET command
+#[derive(Debug, Clone)]
+pub struct Usage<'doc> {
+    /// the `variable` node, or the `variable_name` node in a shell fragment
+    pub node: Node<'doc>,
+    pub range: tree_sitter::Range,
+    pub name: String,
+    pub in_shell: bool,
+}
+
+pub fn declarations(doc: &Document) -> Vec<Declaration<'_>> {
+    let query = declaration_query();
+    let command_idx = query.capture_index_for_name("command").unwrap();
+    let name_idx = query.capture_index_for_name("name").unwrap();
+    let mut query_cursor = QueryCursor::new();
+    query_cursor
+        .matches(query, doc.tree.root_node(), RopeProvider(doc.rope.slice(..)))
+        .filter_map(|m| {
+            let command = m.nodes_for_capture_index(command_idx).next()?;
+            let name_node = m.nodes_for_capture_index(name_idx).next()?;
+            Some(Declaration { command, name_node, name: doc.node_content(name_node) })
+        })
+        .collect()
+}
+
+pub fn usages(doc: &Document) -> Vec<Usage<'_>> {
+    let usage = |node, in_shell| Usage {
+        node,
+        range: node.range(),
+        name: doc.node_content(node),
+        in_shell,
+    };
+    doc.captures(usage_query())
+        .into_iter()
+        .map(|node| usage(node, false))
+        .chain(doc.bash_captures(bash_usage_query()).into_iter().map(|node| usage(node, true)))
+        .collect()
+}
+
+/// The target containing the given position, or `None` for the base target
+pub fn target_at(doc: &Document, pos: Point) -> Option<Node<'_>> {
+    let root = doc.tree.root_node();
+    let mut cursor = root.walk();
+    let target = root
+        .children(&mut cursor)
+        .find(|n| n.kind() == "target" && n.start_position() <= pos && pos < n.end_position());
+    target
+}
+
+/// The targets of the same Earthfile a target is built FROM, directly or through other targets, closest first. The FROM
+/// commands are followed up to `MAX_FROM_DEPTH` targets away.
+pub fn from_targets<'doc>(doc: &'doc Document, target: Node<'doc>) -> Vec<Node<'doc>> {
+    from_chain(doc, &targets(doc), &local_froms(doc), target)
+}
+
+fn from_chain<'doc>(
+    doc: &'doc Document,
+    targets: &[Node<'doc>],
+    froms: &[Reference<'doc>],
+    target: Node<'doc>,
+) -> Vec<Node<'doc>> {
+    let mut res: Vec<Node> = Vec::new();
+    let mut current = vec![target];
+    for _ in 0..MAX_FROM_DEPTH {
+        let mut next = Vec::new();
+        for r in froms {
+            if !enclosing_target(r.node).is_some_and(|t| current.contains(&t)) {
+                continue;
+            }
+            let from_target = targets.iter().find(|t| {
+                t.child_by_field_name("name").is_some_and(|n| doc.node_content(n) == r.name)
+            });
+            if let Some(&from_target) = from_target {
+                if from_target != target && !res.contains(&from_target) {
+                    res.push(from_target);
+                    next.push(from_target);
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        current = next;
+    }
+    res
+}
+
+fn targets(doc: &Document) -> Vec<Node<'_>> {
+    target_names(doc).into_iter().filter_map(|n| n.parent()).collect()
+}
+
+/// The references of the FROM commands to the targets of the same Earthfile
+fn local_froms(doc: &Document) -> Vec<Reference<'_>> {
+    references(doc)
+        .into_iter()
+        .filter(|r| {
+            r.earthfile.is_none() && r.node.parent().map(|p| p.kind()) == Some("from_command")
+        })
+        .collect()
+}
+
+/// The variables declared in a document, with the ENV variables inherited by each target, collected once to resolve
+/// all the variables of the document
+pub struct Scopes<'doc> {
+    doc: &'doc Document,
+    declarations: Vec<Declaration<'doc>>,
+    /// the inherited ENV variables, by target node id
+    inherited: HashMap<usize, Vec<Declaration<'doc>>>,
+}
+
+impl<'doc> Scopes<'doc> {
+    pub fn new(doc: &'doc Document) -> Self {
+        let declarations = declarations(doc);
+        let envs: Vec<_> =
+            declarations.iter().filter(|d| d.command.kind() == "env_command").collect();
+        let base_env: Vec<_> = envs.iter().filter(|d| d.target().is_none()).collect();
+        let targets = targets(doc);
+        let froms = local_froms(doc);
+        let inherited = targets
+            .iter()
+            .map(|&target| {
+                let mut env: Vec<_> = base_env.iter().map(|&&d| d.clone()).collect();
+                for from_target in from_chain(doc, &targets, &froms, target).into_iter().rev() {
+                    env.extend(
+                        envs.iter().filter(|d| d.target() == Some(from_target)).map(|&d| d.clone()),
+                    );
+                }
+                (target.id(), env)
+            })
+            .collect();
+        Scopes { doc, declarations, inherited }
+    }
+
+    pub fn declarations(&self) -> &[Declaration<'doc>] {
+        &self.declarations
+    }
+
+    /// The ENV variables a target inherits from the base target and from the targets of the same Earthfile it is
+    /// built FROM, by increasing precedence. The base target inherits nothing.
+    pub fn inherited_env(&self, target: Option<Node>) -> &[Declaration<'doc>] {
+        target.and_then(|t| self.inherited.get(&t.id())).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Find the declaration of a variable used at the given position: the last one before that position in the same
+    /// target, a global ARG, or an inherited ENV variable
+    pub fn resolve(&self, name: &str, pos: Point) -> Option<&Declaration<'doc>> {
+        let target = target_at(self.doc, pos);
+        let mut declarations = self.declarations.iter().filter(|d| d.name == name);
+        declarations
+            .clone()
+            .rev()
+            .find(|d| d.target() == target && d.command.start_position() < pos)
+            .or_else(|| declarations.find(|d| target.is_some() && d.is_global()))
+            .or_else(|| self.inherited_env(target).iter().rev().find(|d| d.name == name))
+    }
+
+    /// The variables that can be used at the given position: the ones declared before in the same target, the global
+    /// ARGs and the inherited ENV variables. Only one declaration of a variable is kept, in that order of precedence.
+    pub fn visible(&self, pos: Point) -> Vec<Declaration<'doc>> {
+        let target = target_at(self.doc, pos);
+        let globals = self.declarations.iter().filter(|d| target.is_some() && d.is_global());
+        let locals = self
+            .declarations
+            .iter()
+            .filter(|d| d.target() == target && d.command.start_position() < pos);
+        // by increasing precedence, so the last declaration of a variable is the one to keep
+        let mut res: Vec<Declaration> = Vec::new();
+        for d in self.inherited_env(target).iter().chain(globals).chain(locals) {
+            res.retain(|other| other.name != d.name);
+            res.push(d.clone());
+        }
+        res
+    }
+}
+
+/// The ENV variables a target inherits, see `Scopes::inherited_env()`
+pub fn inherited_env<'doc>(
+    doc: &'doc Document,
+    target: Option<Node<'doc>>,
+) -> Vec<Declaration<'doc>> {
+    Scopes::new(doc).inherited_env(target).to_vec()
+}
+
+/// Find the declaration of a variable used at the given position, see `Scopes::resolve()`
+pub fn resolve<'doc>(doc: &'doc Document, name: &str, pos: Point) -> Option<Declaration<'doc>> {
+    Scopes::new(doc).resolve(name, pos).cloned()
+}
+
+/// The variables that can be used at the given position, see `Scopes::visible()`
+pub fn visible_declarations(doc: &Document, pos: Point) -> Vec<Declaration<'_>> {
+    Scopes::new(doc).visible(pos)
+}
+
+/// The declaration of the variable declared or used at the given position
+pub fn declaration_at(doc: &Document, pos: Point) -> Option<Declaration<'_>> {
+    let contains = |r: tree_sitter::Range| r.start_point <= pos && pos <= r.end_point;
+    if let Some(d) = declarations(doc).into_iter().find(|d| contains(d.name_node.range())) {
+        return Some(d);
+    }
+    let usage = usages(doc).into_iter().find(|u| contains(u.range))?;
+    resolve(doc, &usage.name, usage.range.start_point)
+}
+
+/// The usages resolving to the given declaration
+pub fn declaration_usages<'doc>(
+    doc: &'doc Document,
+    declaration: &Declaration,
+) -> Vec<Usage<'doc>> {
+    let scopes = Scopes::new(doc);
+    usages(doc)
+        .into_iter()
+        .filter(|u| {
+            u.name == declaration.name
+                && scopes
+                    .resolve(&u.name, u.range.start_point)
+                    .is_some_and(|d| d.name_node == declaration.name_node)
+        })
+        .collect()
+}
+
+fn has_option(command: Node, option: &str) -> bool {
+    let Some(options) = command.child_by_field_name("options") else {
+        return false;
+    };
+    let mut cursor = options.walk();
+    let res = options.children(&mut cursor).any(|n| n.kind() == option);
+    res
+}
+
+fn declaration_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(
+            &crate::parser::language(),
+            r"(arg_command name: (variable) @name) @command
+              (let_command name: (variable) @name) @command
+              (env_command key: (variable) @name) @command
+              (for_command name: (variable) @name) @command",
+        )
+        .unwrap()
+    })
+}
+
+fn usage_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(
+            &crate::parser::language(),
+            r"(expansion (variable) @usage)
+              (set_command name: (variable) @usage)",
+        )
+        .unwrap()
+    })
+}
+
+fn bash_usage_query() -> &'static Query {
+    static QUERY: OnceLock<Query> = OnceLock::new();
+    QUERY.get_or_init(|| {
+        Query::new(
+            &crate::bash_parser::language(),
+            r"(simple_expansion (variable_name) @usage)
+              (expansion (variable_name) @usage)",
+        )
+        .unwrap()
+    })
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+async fn prepare(ctx: &mut TestContext, line: u32, character: u32) -> Vec<CallHierarchyItem> {
+    ctx.request::<request::CallHierarchyPrepare>(CallHierarchyPrepareParams {
+        text_document_position_params: TextDocumentPositionParams {
+            position: Position { line, character },
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+        },
+        work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn should_prepare_call_hierarchy() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let items = prepare(&mut ctx, 4, 14).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "test");
+    assert_eq!(items[0].uri, ctx.doc_uri("lib/go/Earthfile"));
+    assert_eq!(items[0].detail, Some("target in lib/go/Earthfile".to_string()));
+    assert_eq!(
+        items[0].selection_range,
+        Range {
+            start: Position { line: 2, character: 0 },
+            end: Position { line: 2, character: 4 }
+        }
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_incoming_calls() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let item = prepare(&mut ctx, 4, 14).await.remove(0);
+    let mut calls = ctx
+        .request::<request::CallHierarchyIncomingCalls>(CallHierarchyIncomingCallsParams {
+            item,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    calls.sort_by(|c1, c2| c1.from.name.cmp(&c2.from.name));
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].from.name, "build");
+    assert_eq!(calls[0].from_ranges.len(), 1);
+    assert_eq!(calls[1].from.name, "docker");
+    assert_eq!(calls[1].from_ranges.len(), 2);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_outgoing_calls() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let item = prepare(&mut ctx, 3, 2).await.remove(0);
+    assert_eq!(item.name, "build");
+    let calls = ctx
+        .request::<request::CallHierarchyOutgoingCalls>(CallHierarchyOutgoingCallsParams {
+            item,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let names: Vec<_> = calls.iter().map(|c| c.to.name.as_str()).collect();
+    assert_eq!(names, vec!["test", "FUNC"]);
+    assert_eq!(calls[1].to.detail, Some("function in lib/go/Earthfile".to_string()));
+    assert_eq!(
+        calls[1].from_ranges,
+        vec![Range {
+            start: Position { line: 5, character: 5 },
+            end: Position { line: 5, character: 15 }
+        }]
+    );
+    // panic!("Don’t panic!");
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use earthlyls::cli::{
+    check::{diagnostics, render},
+    OutputFormat,
+};
+use serde_json::{json, Value};
+use tower_lsp::lsp_types::*;
+
+#[test]
+fn should_check_workspace() {
+    let dir = Path::new("tests").join("workspace").join("undefined");
+    let files = diagnostics(&dir).unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, dir.join("Earthfile"));
+    assert_eq!(files[1].path, dir.join("lib").join("Earthfile"));
+    assert!(files[1].diagnostics.is_empty());
+
+    let ds = &files[0].diagnostics;
+    assert_eq!(ds.len(), 3);
+    assert_eq!(ds[0].message, "undefined target +missing");
+    assert_eq!(ds[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(ds[0].code, Some(NumberOrString::String("undefined_target".to_string())));
+}
+
+#[test]
+fn should_render_json() {
+    let dir = Path::new("tests").join("workspace").join("undefined");
+    let files = diagnostics(&dir).unwrap();
+    let res: Value = serde_json::from_str(&render(&files, OutputFormat::Json)).unwrap();
+    let res = res.as_array().unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0]["path"], dir.join("Earthfile").to_str().unwrap());
+    assert_eq!(res[0]["diagnostics"].as_array().unwrap().len(), 3);
+    assert_eq!(res[0]["diagnostics"][0]["message"], "undefined target +missing");
+    assert_eq!(res[0]["diagnostics"][0]["code"], "undefined_target");
+    assert_eq!(res[0]["diagnostics"][0]["severity"], 1);
+    assert_eq!(res[1]["diagnostics"], json!([]));
+}
+
+#[test]
+fn should_render_sarif() {
+    let dir = Path::new("tests").join("workspace").join("undefined");
+    let files = diagnostics(&dir).unwrap();
+    let res: Value = serde_json::from_str(&render(&files, OutputFormat::Sarif)).unwrap();
+    assert_eq!(res["version"], "2.1.0");
+    let run = &res["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "earthlyls");
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["ruleId"], "undefined_target");
+    assert_eq!(results[0]["level"], "error");
+    assert_eq!(results[0]["message"]["text"], "undefined target +missing");
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "tests/workspace/undefined/Earthfile");
+    assert_eq!(
+        location["region"],
+        json!({ "startLine": 5, "startColumn": 9, "endLine": 5, "endColumn": 17 })
+    );
+}
+
+#[test]
+fn should_render_text() {
+    let dir = Path::new("tests").join("workspace").join("undefined");
+    let files = diagnostics(&dir).unwrap();
+    let res = render(&files, OutputFormat::Text);
+    assert_eq!(res.lines().count(), 3);
+    assert!(res.starts_with(&format!(
+        "{}:5:9: error: undefined target +missing [undefined_target]\n",
+        dir.join("Earthfile").display()
+    )));
+}
+
+#[test]
+fn should_fail_on_errors() {
+    let workspace = Path::new("tests").join("workspace");
+    assert!(
+        !earthlyls::cli::check::check(&workspace.join("undefined"), OutputFormat::Sarif).unwrap()
+    );
+    assert!(earthlyls::cli::check::check(&workspace.join("import"), OutputFormat::Text).unwrap());
+}
+
+#[test]
+fn should_not_check_excluded_files() {
+    let dir = Path::new("tests").join("workspace").join("project_config");
+    let files = diagnostics(&dir).unwrap();
+    let paths: Vec<_> = files.iter().map(|f| f.path.clone()).collect();
+    assert_eq!(paths, vec![dir.join("Earthfile")]);
+}
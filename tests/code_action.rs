@@ -0,0 +1,68 @@
+mod common;
+
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+#[tokio::test]
+async fn should_provide_quick_fixes() {
+    let mut ctx = TestContext::new("syntax");
+    ctx.initialize().await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    let uri = ctx.doc_uri("Earthfile");
+    let res = ctx
+        .request::<request::CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: Range::default(),
+            context: CodeActionContext {
+                diagnostics: dp.diagnostics,
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let actions: Vec<_> = res
+        .into_iter()
+        .map(|a| match a {
+            CodeActionOrCommand::CodeAction(a) => a,
+            CodeActionOrCommand::Command(_) => panic!("not a code action!"),
+        })
+        .collect();
+    assert_eq!(actions.len(), 3);
+    let edit = |a: &CodeAction| a.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri][0].clone();
+
+    assert_eq!(actions[0].title, "Replace with --foo=bar");
+    assert_eq!(actions[0].kind, Some(CodeActionKind::QUICKFIX));
+    assert_eq!(
+        edit(&actions[0]),
+        TextEdit {
+            range: Range {
+                start: Position { line: 6, character: 8 },
+                end: Position { line: 6, character: 27 }
+            },
+            new_text: "--foo=bar".to_string()
+        }
+    );
+
+    assert_eq!(actions[1].title, "Remove --broken-option");
+    assert_eq!(
+        edit(&actions[1]),
+        TextEdit {
+            range: Range {
+                start: Position { line: 3, character: 6 },
+                end: Position { line: 3, character: 22 }
+            },
+            new_text: String::new()
+        }
+    );
+
+    assert_eq!(actions[2].title, "Add VERSION 0.8");
+    assert_eq!(
+        edit(&actions[2]),
+        TextEdit { range: Range::default(), new_text: "VERSION 0.8\n".to_string() }
+    );
+    // panic!("Don’t panic!");
+}
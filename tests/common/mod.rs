@@ -12,9 +12,9 @@ use temp_dir::TempDir;
 use tokio::io::{duplex, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
 use tower_lsp::lsp_types::notification::Notification;
 use tower_lsp::lsp_types::{InitializedParams, Url, WorkspaceFolder};
-use tower_lsp::{jsonrpc, lsp_types, lsp_types::request::Request, LspService, Server};
+use tower_lsp::{jsonrpc, lsp_types, lsp_types::request::Request, Server};
 
-use earthlyls::backend::Backend;
+use earthlyls::backend;
 
 fn encode_message(content_type: Option<&str>, message: &str) -> String {
     let content_type = content_type.map(|ty| format!("\r\nContent-Type: {ty}")).unwrap_or_default();
@@ -35,8 +35,7 @@ impl TestContext {
         let (resp_server, response_rx) = duplex(1024);
         let response_rx = BufReader::new(response_rx);
 
-        let (service, socket) =
-            LspService::build(|client| Backend::new(client, "0.1.0".into())).finish();
+        let (service, socket) = backend::service("0.1.0".into());
         let server = tokio::spawn(Server::new(req_server, resp_server, socket).serve(service));
 
         // create a temporary workspace an init it with our test inputs
@@ -62,6 +61,11 @@ impl TestContext {
     }
 
     pub async fn response<R: std::fmt::Debug + serde::de::DeserializeOwned>(&mut self) -> R {
+        serde_json::from_value(self.response_result().await.unwrap()).unwrap()
+    }
+
+    /// The result of the next response, or its error
+    pub async fn response_result(&mut self) -> jsonrpc::Result<serde_json::Value> {
         loop {
             // first line is the content length header
             let mut clh = String::new();
@@ -79,13 +83,15 @@ impl TestContext {
             let content = String::from_utf8(content).unwrap();
             eprintln!("received: {content}");
             std::io::stderr().flush().unwrap();
-            // skip log messages
-            if content.contains("window/logMessage") {
+            // skip log messages and diagnostics
+            if content.contains("window/logMessage")
+                || content.contains("textDocument/publishDiagnostics")
+            {
                 continue;
             }
             let response = serde_json::from_str::<jsonrpc::Response>(&content).unwrap();
             let (_id, result) = response.into_parts();
-            return serde_json::from_value(result.unwrap()).unwrap();
+            return result;
         }
     }
 
@@ -102,6 +108,17 @@ impl TestContext {
         self.response().await
     }
 
+    /// Send a request expected to fail, and return its error
+    pub async fn request_error<R: Request>(&mut self, params: R::Params) -> jsonrpc::Error {
+        let request = jsonrpc::Request::build(R::METHOD)
+            .id(self.request_id)
+            .params(serde_json::to_value(params).unwrap())
+            .finish();
+        self.request_id += 1;
+        self.send(&request).await;
+        self.response_result().await.unwrap_err()
+    }
+
     pub async fn recv<R: std::fmt::Debug + serde::de::DeserializeOwned>(&mut self) -> R {
         loop {
             // first line is the content length header
@@ -138,6 +155,14 @@ impl TestContext {
     }
 
     pub async fn initialize(&mut self) {
+        self.initialize_with(|_| ()).await
+    }
+
+    /// Initialize the server with some changes in the initialize params
+    pub async fn initialize_with(
+        &mut self,
+        update: impl FnOnce(&mut <lsp_types::request::Initialize as Request>::Params),
+    ) {
         // a real set of initialize param from helix. We just have to change the workspace configuration
         let initialize = r#"{
         "capabilities": {
@@ -288,6 +313,7 @@ impl TestContext {
         initialize.root_uri = Some(workspace_url.clone());
         initialize.workspace_folders =
             Some(vec![WorkspaceFolder { name: "tmp".to_owned(), uri: workspace_url.clone() }]);
+        update(&mut initialize);
         self.request::<lsp_types::request::Initialize>(initialize).await;
         self.notify::<lsp_types::notification::Initialized>(InitializedParams {}).await;
     }
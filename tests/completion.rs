@@ -0,0 +1,205 @@
+mod common;
+
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+async fn complete(ctx: &mut TestContext, line: u32, character: u32) -> Option<Vec<CompletionItem>> {
+    let uri = ctx.doc_uri("Earthfile");
+    let res = ctx
+        .request::<request::Completion>(CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line, character },
+                text_document: TextDocumentIdentifier { uri },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        })
+        .await;
+    res.map(|res| match res {
+        CompletionResponse::Array(items) => items,
+        CompletionResponse::List(list) => list.items,
+    })
+}
+
+#[tokio::test]
+async fn should_complete_options() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 9, 11).await.unwrap();
+    let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+    assert!(labels.contains(&"--dir"));
+    assert!(labels.contains(&"--keep-ts"));
+    assert!(labels.contains(&"--if-exists"));
+    assert!(!labels.contains(&"--push"));
+    let dir = items.iter().find(|i| i.label == "--dir").unwrap();
+    let Some(Documentation::MarkupContent(doc)) = &dir.documentation else {
+        panic!("not a markup content!")
+    };
+    assert!(doc.value.contains("directories"));
+    assert!(!doc.value.contains("build-arg"));
+    assert_eq!(
+        dir.text_edit,
+        Some(CompletionTextEdit::Edit(TextEdit {
+            range: Range {
+                start: Position { line: 9, character: 9 },
+                end: Position { line: 9, character: 11 }
+            },
+            new_text: "--dir".to_string()
+        }))
+    );
+
+    let items = complete(&mut ctx, 10, 16).await.unwrap();
+    let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+    assert!(labels.contains(&"--secret"));
+    assert!(labels.contains(&"--mount"));
+    assert!(labels.contains(&"--privileged"));
+
+    // on a continued line
+    let items = complete(&mut ctx, 13, 12).await.unwrap();
+    let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+    assert!(labels.contains(&"--platform"));
+    assert!(labels.contains(&"--allow-privileged"));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_not_complete_options_after_arguments() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 11, 22).await.unwrap_or_default();
+    assert!(!items.iter().any(|i| i.label.starts_with('-')));
+    // panic!("Don’t panic!");
+}
+
+fn labels(items: &[CompletionItem]) -> Vec<&str> {
+    items.iter().map(|i| i.label.as_str()).collect()
+}
+
+#[tokio::test]
+async fn should_complete_targets() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 14, 11).await.unwrap();
+    assert_eq!(labels(&items), vec!["build"]);
+    let items = complete(&mut ctx, 16, 15).await.unwrap();
+    assert_eq!(labels(&items), vec!["image", "test"]);
+    let items = complete(&mut ctx, 18, 12).await.unwrap();
+    assert_eq!(labels(&items), vec!["build"]);
+    assert_eq!(
+        items[0].text_edit,
+        Some(CompletionTextEdit::Edit(TextEdit {
+            range: Range {
+                start: Position { line: 18, character: 10 },
+                end: Position { line: 18, character: 12 }
+            },
+            new_text: "build".to_string()
+        }))
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_complete_functions() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 15, 8).await.unwrap();
+    assert_eq!(labels(&items), vec!["FUNC"]);
+    let items = complete(&mut ctx, 17, 13).await.unwrap();
+    assert_eq!(labels(&items), vec!["LIB_FUNC"]);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_complete_earthfile_paths() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 19, 12).await.unwrap();
+    assert_eq!(labels(&items), vec!["lib", "services/api", "services/web"]);
+    let items = complete(&mut ctx, 20, 15).await.unwrap();
+    assert_eq!(labels(&items), vec!["services/api", "services/web"]);
+    assert_eq!(
+        items[0].text_edit,
+        Some(CompletionTextEdit::Edit(TextEdit {
+            range: Range {
+                start: Position { line: 20, character: 13 },
+                end: Position { line: 20, character: 15 }
+            },
+            new_text: "services/api".to_string()
+        }))
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_complete_variables() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 9, 14).await.unwrap();
+    let labels = labels(&items);
+    assert_eq!(labels[..4], ["VERSION", "NAME", "greeting", "HOME"]);
+    assert!(labels.contains(&"EARTHLY_GIT_HASH"));
+    assert!(labels.contains(&"TARGETARCH"));
+    assert!(!labels.contains(&"LOCAL"));
+    assert!(!labels.contains(&"dir"));
+    assert_eq!(items[1].detail, Some("ARG NAME=world".to_string()));
+    let Some(Documentation::MarkupContent(doc)) = &items[1].documentation else {
+        panic!("not a markup content!")
+    };
+    assert_eq!(
+        doc.value,
+        "Declared with `ARG` in target `build`, line 6\n\nDefault value: `world`"
+    );
+    assert_eq!(
+        items[1].text_edit,
+        Some(CompletionTextEdit::Edit(TextEdit {
+            range: Range {
+                start: Position { line: 9, character: 13 },
+                end: Position { line: 9, character: 14 }
+            },
+            new_text: "NAME".to_string()
+        }))
+    );
+
+    // in a shell fragment
+    let items = complete(&mut ctx, 12, 12).await.unwrap();
+    assert_eq!(self::labels(&items)[..5], ["VERSION", "NAME", "greeting", "HOME", "dir"]);
+
+    // in another target
+    let items = complete(&mut ctx, 17, 13).await.unwrap();
+    assert_eq!(self::labels(&items)[..2], ["VERSION", "EARTHLY_BUILD_SHA"]);
+
+    // not in a comment
+    assert!(complete(&mut ctx, 25, 10).await.is_none());
+
+    // after some non ascii characters
+    assert!(complete(&mut ctx, 26, 16).await.is_some());
+    assert!(complete(&mut ctx, 27, 7).await.is_none());
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_complete_build_args() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let items = complete(&mut ctx, 21, 41).await.unwrap();
+    assert_eq!(labels(&items), vec!["--REGISTRY", "--PLATFORM"]);
+    assert_eq!(items[0].detail, Some("default: docker.io".to_string()));
+    assert_eq!(items[1].detail, Some("default: linux/amd64".to_string()));
+    assert_eq!(
+        items[1].text_edit,
+        Some(CompletionTextEdit::Edit(TextEdit {
+            range: Range {
+                start: Position { line: 21, character: 39 },
+                end: Position { line: 21, character: 41 }
+            },
+            new_text: "--PLATFORM=".to_string()
+        }))
+    );
+
+    let items = complete(&mut ctx, 22, 23).await.unwrap();
+    assert_eq!(labels(&items), vec!["--MESSAGE"]);
+    assert_eq!(items[0].detail, Some("optional".to_string()));
+    // panic!("Don’t panic!");
+}
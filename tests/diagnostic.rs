@@ -11,7 +11,7 @@ async fn should_publish_syntax_diagnostics() {
     let dp = ctx.recv::<PublishDiagnosticsParams>().await;
     assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
     let ds = dp.diagnostics;
-    assert_eq!(ds.len(), 5);
+    assert_eq!(ds.len(), 6);
 
     let d = &ds[0];
     assert_eq!(d.range.start.line, 6);
@@ -48,5 +48,133 @@ async fn should_publish_syntax_diagnostics() {
     assert_eq!(d.range.end.character, 0);
     assert_eq!(d.message, "no version specified");
 
+    let d = &ds[5];
+    assert_eq!(d.range.start.line, 6);
+    assert_eq!(d.range.start.character, 28);
+    assert_eq!(d.range.end.line, 6);
+    assert_eq!(d.range.end.character, 32);
+    assert_eq!(d.message, "undefined target +bar");
+
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_publish_undefined_target_diagnostics() {
+    let mut ctx = TestContext::new("undefined");
+    ctx.initialize().await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    let ds = dp.diagnostics;
+    assert_eq!(ds.len(), 3);
+
+    let d = &ds[0];
+    assert_eq!(d.range.start.line, 4);
+    assert_eq!(d.range.start.character, 8);
+    assert_eq!(d.range.end.line, 4);
+    assert_eq!(d.range.end.character, 16);
+    assert_eq!(d.message, "undefined target +missing");
+
+    let d = &ds[1];
+    assert_eq!(d.range.start.line, 6);
+    assert_eq!(d.range.start.character, 7);
+    assert_eq!(d.range.end.line, 6);
+    assert_eq!(d.range.end.character, 20);
+    assert_eq!(d.message, "undefined target +nothing");
+
+    let d = &ds[2];
+    assert_eq!(d.range.start.line, 7);
+    assert_eq!(d.message, "undefined function +MISSING");
+
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_publish_undeclared_variable_diagnostics() {
+    let mut ctx = TestContext::new("undeclared");
+    ctx.initialize().await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    let ds = dp.diagnostics;
+    // the variables of the golang image are not reported in the go targets
+    assert_eq!(ds.len(), 5);
+
+    let d = &ds[0];
+    assert_eq!(d.range.start.line, 8);
+    assert_eq!(d.range.start.character, 14);
+    assert_eq!(d.range.end.line, 8);
+    assert_eq!(d.range.end.character, 19);
+    assert_eq!(d.message, "undeclared variable LOCAL");
+    assert_eq!(d.severity, Some(DiagnosticSeverity::WARNING));
+
+    let d = &ds[1];
+    assert_eq!(d.range.start.line, 8);
+    assert_eq!(d.range.start.character, 21);
+    assert_eq!(d.message, "undeclared variable MISSING");
+
+    let d = &ds[2];
+    assert_eq!(d.range.start.line, 13);
+    assert_eq!(d.range.start.character, 14);
+    assert_eq!(d.message, "undeclared variable NAME");
+
+    // built from an image, only the variables never declared in the Earthfile may come from the image
+    let d = &ds[3];
+    assert_eq!(d.range.start.line, 25);
+    assert_eq!(d.message, "undeclared variable LOCAL");
+    let d = &ds[4];
+    assert_eq!(d.range.start.line, 25);
+    assert_eq!(d.message, "undeclared variable NAME");
+}
+
+#[tokio::test]
+async fn should_publish_dependency_cycle_diagnostics() {
+    let mut ctx = TestContext::new("cycle");
+    ctx.initialize().await;
+    let mut dps = [
+        ctx.recv::<PublishDiagnosticsParams>().await,
+        ctx.recv::<PublishDiagnosticsParams>().await,
+    ];
+    dps.sort_by(|dp1, dp2| dp1.uri.cmp(&dp2.uri));
+    assert_eq!(dps[0].uri, ctx.doc_uri("Earthfile"));
+    let ds = &dps[0].diagnostics;
+    assert_eq!(ds.len(), 2);
+
+    let d = &ds[0];
+    assert_eq!(d.range.start.line, 3);
+    assert_eq!(d.range.start.character, 10);
+    assert_eq!(d.message, "dependency cycle: +a -> x+b -> +a");
+    assert_eq!(d.severity, Some(DiagnosticSeverity::ERROR));
+    let related = d.related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].location.uri, ctx.doc_uri("x/Earthfile"));
+    assert_eq!(related[0].location.range.start, Position { line: 3, character: 9 });
+    assert_eq!(related[0].message, "FROM +a in x+b");
+
+    // a target depending on itself
+    let d = &ds[1];
+    assert_eq!(d.range.start.line, 7);
+    assert_eq!(d.message, "dependency cycle: +c -> +c");
+
+    assert_eq!(dps[1].uri, ctx.doc_uri("x/Earthfile"));
+    let ds = &dps[1].diagnostics;
+    assert_eq!(ds.len(), 1);
+    assert_eq!(ds[0].message, "dependency cycle: x+b -> +a -> x+b");
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_publish_all_the_references_of_cycles_sharing_a_target() {
+    let mut ctx = TestContext::new("shared_cycle");
+    ctx.initialize().await;
+    let ds = ctx.recv::<PublishDiagnosticsParams>().await.diagnostics;
+    let messages: Vec<_> = ds.iter().map(|d| (d.range.start.line, d.message.as_str())).collect();
+    assert_eq!(
+        messages,
+        vec![
+            (3, "dependency cycle: +a -> +b -> +a"),
+            (4, "dependency cycle: +a -> +c -> +a"),
+            (7, "dependency cycle: +b -> +a -> +b"),
+            (10, "dependency cycle: +c -> +a -> +c"),
+        ]
+    );
     // panic!("Don’t panic!");
 }
@@ -70,3 +70,79 @@ async fn should_provide_document_symbols() {
     assert_eq!(s.container_name, Some("docker".to_string()));
     // panic!("Don’t panic!");
 }
+
+#[tokio::test]
+async fn should_not_provide_import_aliases_in_flat_document_symbols() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::DocumentSymbolRequest>(DocumentSymbolParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let DocumentSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["build", "docker"]);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_nested_document_symbols() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize_with(|params| {
+        params.capabilities.text_document.as_mut().unwrap().document_symbol =
+            Some(DocumentSymbolClientCapabilities {
+                hierarchical_document_symbol_support: Some(true),
+                ..Default::default()
+            });
+    })
+    .await;
+    let res = ctx
+        .request::<request::DocumentSymbolRequest>(DocumentSymbolParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let DocumentSymbolResponse::Nested(symbols) = res else {
+        panic!("not a nested response!");
+    };
+    let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["golib", "build", "docker"]);
+    assert_eq!(symbols[0].kind, SymbolKind::MODULE);
+    assert_eq!(symbols[0].detail, Some("./lib/go".to_string()));
+    let s = &symbols[2];
+    assert_eq!(s.kind, SymbolKind::FUNCTION);
+    assert_eq!(s.detail, Some("target".to_string()));
+    assert_eq!(
+        s.range,
+        Range {
+            start: Position { line: 7, character: 0 },
+            end: Position { line: 11, character: 0 }
+        }
+    );
+    assert_eq!(
+        s.selection_range,
+        Range {
+            start: Position { line: 7, character: 0 },
+            end: Position { line: 7, character: 6 }
+        }
+    );
+    let children = s.children.as_ref().unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].name, "go");
+    assert_eq!(
+        children[0].selection_range,
+        Range {
+            start: Position { line: 8, character: 9 },
+            end: Position { line: 8, character: 17 }
+        }
+    );
+    // panic!("Don’t panic!");
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use earthlyls::{cli::fmt::fmt, format::indent};
+use temp_dir::TempDir;
+
+#[test]
+fn should_format_with_the_given_indent() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("Earthfile");
+    std::fs::copy(Path::new("tests").join("workspace").join("format").join("Earthfile"), &path)
+        .unwrap();
+    let paths = [path.clone()];
+    assert!(fmt(false, &indent(2, true), &paths).unwrap());
+    assert!(std::fs::read_to_string(&path).unwrap().contains("\nbuild:\n  ARG NAME=world\n"));
+    // the file is formatted for an indentation of 2 spaces only
+    assert!(fmt(true, &indent(2, true), &paths).unwrap());
+    assert!(!fmt(true, &indent(4, true), &paths).unwrap());
+    assert!(!fmt(true, &indent(4, false), &paths).unwrap());
+}
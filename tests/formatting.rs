@@ -0,0 +1,76 @@
+mod common;
+
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+#[tokio::test]
+async fn should_format_document() {
+    let mut ctx = TestContext::new("format");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::Formatting>(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            options: FormattingOptions { tab_size: 4, insert_spaces: true, ..Default::default() },
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].range.start, Position { line: 0, character: 0 });
+    assert_eq!(res[0].range.end, Position { line: 20, character: 0 });
+    assert_eq!(
+        res[0].new_text,
+        r#"VERSION 0.8
+FROM alpine:3.18
+
+build:
+    ARG NAME=world
+    RUN echo   "a  b" \
+        && echo $NAME
+    IF [ "$NAME" = "world" ]
+        RUN echo yes
+    ELSE
+        RUN echo no # trailing
+    END
+    FOR dir IN a b c
+        BUILD --platform=linux/amd64 \
+            ./$dir+build
+    END
+
+# doc for test
+test:
+    FROM +build
+"#
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_format_range() {
+    let mut ctx = TestContext::new("format");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::RangeFormatting>(DocumentRangeFormattingParams {
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            range: Range {
+                start: Position { line: 19, character: 0 },
+                end: Position { line: 19, character: 4 },
+            },
+            options: FormattingOptions { tab_size: 2, insert_spaces: true, ..Default::default() },
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![TextEdit {
+            range: Range {
+                start: Position { line: 18, character: 0 },
+                end: Position { line: 20, character: 0 }
+            },
+            new_text: "test:\n  FROM +build\n".to_string()
+        }]
+    );
+    // panic!("Don’t panic!");
+}
@@ -55,3 +55,173 @@ async fn should_goto_multiple_definitions() {
     assert_eq!(definitions.len(), 2);
     // panic!("Don’t panic!");
 }
+
+#[tokio::test]
+async fn should_goto_definition_with_import_alias() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 4, character: 14 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let GotoDefinitionResponse::Link(definitions) = res else { panic!("not a link variant!") };
+    assert_eq!(definitions.len(), 1);
+    let definition = &definitions[0];
+    assert_eq!(definition.target_uri, ctx.doc_uri("lib/go/Earthfile"));
+    assert_eq!(definition.target_range.start.line, 2);
+    let Some(origin_range) = definition.origin_selection_range else { panic!("no origin range!") };
+    assert_eq!(origin_range.start.line, 4);
+    assert_eq!(origin_range.start.character, 8);
+    assert_eq!(origin_range.end.line, 4);
+    assert_eq!(origin_range.end.character, 18);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_goto_definition_with_inferred_target_import_alias() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 9, character: 10 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let GotoDefinitionResponse::Link(definitions) = res else { panic!("not a link variant!") };
+    assert_eq!(definitions.len(), 1);
+    let definition = &definitions[0];
+    assert_eq!(definition.target_uri, ctx.doc_uri("lib/go/Earthfile"));
+    assert_eq!(definition.target_range.start.line, 2);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_goto_imported_earthfile() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 1, character: 10 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let GotoDefinitionResponse::Link(definitions) = res else { panic!("not a link variant!") };
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].target_uri, ctx.doc_uri("lib/go/Earthfile"));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_goto_variable_declaration() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 9, character: 14 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let GotoDefinitionResponse::Link(definitions) = res else { panic!("not a link variant!") };
+    assert_eq!(definitions.len(), 1);
+    let definition = &definitions[0];
+    assert_eq!(definition.target_uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(
+        definition.target_selection_range,
+        Range {
+            start: Position { line: 5, character: 6 },
+            end: Position { line: 5, character: 10 }
+        }
+    );
+    let Some(origin_range) = definition.origin_selection_range else { panic!("no origin range!") };
+    assert_eq!(origin_range.start.line, 9);
+    assert_eq!(origin_range.start.character, 13);
+    assert_eq!(origin_range.end.character, 17);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_goto_global_variable_declaration() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 16, character: 16 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let GotoDefinitionResponse::Link(definitions) = res else { panic!("not a link variant!") };
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].target_selection_range.start.line, 1);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_not_goto_variable_declaration_out_of_scope() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 17, character: 13 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await;
+    assert!(res.is_none());
+}
+
+#[tokio::test]
+async fn should_goto_inherited_variable_declaration() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 24, character: 13 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let GotoDefinitionResponse::Link(definitions) = res else { panic!("not a link variant!") };
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(
+        definitions[0].target_selection_range,
+        Range {
+            start: Position { line: 20, character: 6 },
+            end: Position { line: 20, character: 11 }
+        }
+    );
+    // panic!("Don’t panic!");
+}
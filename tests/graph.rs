@@ -0,0 +1,116 @@
+mod common;
+
+use std::path::Path;
+
+use earthlyls::{
+    cli::graph::target_graph,
+    commands::target_graph::{TargetGraphParams, TargetGraphRequest},
+    graph::{EdgeKind, GraphEdge, GraphFormat},
+};
+use serde_json::json;
+
+use crate::common::*;
+
+#[test]
+fn should_build_target_graph() {
+    let graph = target_graph(&Path::new("tests").join("workspace").join("import")).unwrap();
+    let ids: Vec<_> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["+build", "+docker", "lib/go+FUNC", "lib/go+test"]);
+    assert!(graph.nodes[2].function);
+    assert_eq!(graph.nodes[3].earthfile, "lib/go/Earthfile");
+    let edge = |from: &str, to: &str, kind| GraphEdge { from: from.into(), to: to.into(), kind };
+    assert_eq!(
+        graph.edges,
+        vec![
+            edge("+build", "lib/go+FUNC", EdgeKind::Do),
+            edge("+build", "lib/go+test", EdgeKind::Build),
+            edge("+docker", "lib/go+test", EdgeKind::From),
+            edge("+docker", "lib/go+test", EdgeKind::Copy),
+        ]
+    );
+    let dot = graph.render(GraphFormat::Dot);
+    assert!(dot.starts_with("digraph earthly {\n"));
+    assert!(dot.contains("    \"+build\" -> \"lib/go+FUNC\" [label=\"DO\"];\n"));
+    let mermaid = graph.render(GraphFormat::Mermaid);
+    assert!(mermaid.contains("    n2([\"lib/go+FUNC\"])\n"));
+    assert!(mermaid.contains("    n0 -->|DO| n2\n"));
+
+    let mut graph = graph;
+    graph.edges.push(edge("+build", "+missing", EdgeKind::Build));
+    let mermaid = graph.render(GraphFormat::Mermaid);
+    assert!(!mermaid.contains("-->|BUILD| n0\n"));
+    assert_eq!(mermaid.matches("-->").count(), 4);
+}
+
+#[test]
+fn should_not_graph_excluded_files() {
+    let graph = target_graph(&Path::new("tests").join("workspace").join("project_config")).unwrap();
+    let ids: Vec<_> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["+build"]);
+}
+
+#[test]
+fn should_label_docker_references() {
+    let graph = target_graph(&Path::new("tests").join("workspace").join("graph")).unwrap();
+    let edge = |from: &str, to: &str, kind| GraphEdge { from: from.into(), to: to.into(), kind };
+    assert_eq!(
+        graph.edges,
+        vec![
+            edge("+docker", "+dockerfile", EdgeKind::FromDockerfile),
+            edge("+test", "+image", EdgeKind::WithDocker),
+        ]
+    );
+    let dot = graph.render(GraphFormat::Dot);
+    assert!(dot.contains("    \"+test\" -> \"+image\" [label=\"WITH DOCKER\"];\n"));
+    assert!(dot.contains("    \"+docker\" -> \"+dockerfile\" [label=\"FROM DOCKERFILE\"];\n"));
+}
+
+#[tokio::test]
+async fn should_identify_remote_targets_by_their_reference() {
+    let mut ctx = TestContext::new("remote");
+    ctx.initialize_with(|params| {
+        let root = params.root_uri.as_ref().unwrap().to_file_path().unwrap();
+        let root = root.display();
+        params.initialization_options = Some(json!({
+            "remoteEarthfiles": {
+                "github.com/earthly/lib": format!("{root}/checkouts/lib"),
+                "github.com/acme/tools": format!("{root}/checkouts/tools"),
+            }
+        }));
+    })
+    .await;
+    let res =
+        ctx.request::<TargetGraphRequest>(TargetGraphParams { format: GraphFormat::Json }).await;
+    let ids: Vec<_> =
+        res["nodes"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "+build",
+            "github.com/acme/tools/go+image",
+            "github.com/earthly/lib+HELLO",
+            "github.com/earthly/lib+all"
+        ]
+    );
+    assert_eq!(res["nodes"][1]["earthfile"], "github.com/acme/tools/go/Earthfile");
+    assert!(res["edges"]
+        .as_array()
+        .unwrap()
+        .contains(&json!({"from": "+build", "to": "github.com/earthly/lib+HELLO", "kind": "DO"})));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_target_graph() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res =
+        ctx.request::<TargetGraphRequest>(TargetGraphParams { format: GraphFormat::Json }).await;
+    assert_eq!(res["nodes"].as_array().unwrap().len(), 4);
+    assert_eq!(res["edges"][0]["kind"], "DO");
+
+    let res =
+        ctx.request::<TargetGraphRequest>(TargetGraphParams { format: GraphFormat::Mermaid }).await;
+    assert!(res.as_str().unwrap().starts_with("flowchart LR\n"));
+    // panic!("Don’t panic!");
+}
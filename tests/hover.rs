@@ -75,3 +75,173 @@ async fn should_not_hover_on_eol() {
         .await;
     assert!(res.is_none());
 }
+
+#[tokio::test]
+async fn should_hover_import_alias() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::HoverRequest>(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 4, character: 9 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let HoverContents::Markup(markup) = res.contents else { panic!("not a markup content!") };
+    assert!(markup.value.contains("./lib/go"));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_hover_target_reference() {
+    let mut ctx = TestContext::new("hover");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::HoverRequest>(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 4, character: 17 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let HoverContents::Markup(markup) = res.contents else { panic!("not a markup content!") };
+    assert_eq!(
+        markup.value,
+        "```earthfile
+image:
+```
+Defined in `lib/Earthfile`
+
+Build the image
+and push it
+
+**ARGs**
+
+* `--REGISTRY` — default: docker.io
+* `--TAG` — required
+* `--PLATFORM` — default: linux/amd64
+
+**Outputs**
+
+* `SAVE ARTIFACT out AS LOCAL out`
+* `SAVE IMAGE --push $REGISTRY/foo:$TAG`"
+    );
+    assert_eq!(
+        res.range,
+        Some(Range {
+            start: Position { line: 4, character: 10 },
+            end: Position { line: 4, character: 21 }
+        })
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_hover_function_reference() {
+    let mut ctx = TestContext::new("hover");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::HoverRequest>(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line: 5, character: 12 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let HoverContents::Markup(markup) = res.contents else { panic!("not a markup content!") };
+    assert!(markup.value.contains("GREET:"));
+    assert!(markup.value.contains("Say hello"));
+    assert!(markup.value.contains("* `--NAME` — default: world"));
+    assert!(!markup.value.contains("Outputs"));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_hover_variable() {
+    let mut ctx = TestContext::new("hover");
+    ctx.initialize().await;
+    // on the usage
+    assert_eq!(
+        hover_markup(&mut ctx, 9, 25).await,
+        "```earthfile\nARG --required VERSION\n```\nDeclared with `ARG` in target `build`\n\n* required"
+    );
+    // on the declaration
+    assert_eq!(
+        hover_markup(&mut ctx, 7, 9).await,
+        "```earthfile\nLET NAME=foo\n```\nDeclared with `LET` in target `build`\n\n* value: `foo`"
+    );
+    // on a SET command
+    assert!(hover_markup(&mut ctx, 8, 9).await.contains("LET NAME"));
+    // the hovered variable is highlighted
+    let range = |line, start, end| {
+        Some(Range {
+            start: Position { line, character: start },
+            end: Position { line, character: end },
+        })
+    };
+    assert_eq!(hover_at(&mut ctx, 9, 25).await.range, range(9, 20, 27));
+    assert_eq!(hover_at(&mut ctx, 7, 9).await.range, range(7, 8, 12));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_hover_inherited_variable() {
+    let mut ctx = TestContext::new("hover");
+    ctx.initialize().await;
+    let hover = hover_at(&mut ctx, 18, 15).await;
+    let HoverContents::Markup(markup) = hover.contents else { panic!("not a markup content!") };
+    assert_eq!(
+        markup.value,
+        "```earthfile\nENV MYENV=1\n```\nDeclared with `ENV` in target `base`\n\n* value: `1`"
+    );
+    assert_eq!(
+        hover.range,
+        Some(Range {
+            start: Position { line: 18, character: 14 },
+            end: Position { line: 18, character: 19 }
+        })
+    );
+    // panic!("Don’t panic!");
+}
+
+async fn hover_markup(ctx: &mut TestContext, line: u32, character: u32) -> String {
+    let res = hover_at(ctx, line, character).await;
+    let HoverContents::Markup(markup) = res.contents else { panic!("not a markup content!") };
+    markup.value
+}
+
+async fn hover_at(ctx: &mut TestContext, line: u32, character: u32) -> Hover {
+    ctx.request::<request::HoverRequest>(HoverParams {
+        text_document_position_params: TextDocumentPositionParams {
+            position: Position { line, character },
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+        },
+        work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn should_hover_option() {
+    let mut ctx = TestContext::new("hover");
+    ctx.initialize().await;
+    let markup = hover_markup(&mut ctx, 10, 12).await;
+    assert!(markup.starts_with("```earthfile\n--keep-ts\n```\n"));
+    assert!(markup.contains("timestamps"));
+    assert!(!markup.contains("--keep-own"));
+
+    let markup = hover_markup(&mut ctx, 11, 20).await;
+    assert!(markup.starts_with("```earthfile\n--mount <mount-spec>\n```\n"));
+
+    let markup = hover_markup(&mut ctx, 11, 9).await;
+    assert!(markup.starts_with("```earthfile\n--push\n```\n"));
+    // panic!("Don’t panic!");
+}
@@ -103,3 +103,109 @@ async fn should_provide_references_with_wildcard() {
     assert_eq!(r.range.end.character, 19);
     // panic!("Don’t panic!");
 }
+
+#[tokio::test]
+async fn should_provide_references_with_import_alias() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let mut res = ctx
+        .request::<request::References>(ReferenceParams {
+            context: ReferenceContext { include_declaration: true },
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 2, character: 1 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("lib/go/Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    res.sort_by_key(|l| (l.uri.to_string(), l.range.start.line));
+    assert_eq!(res.len(), 4);
+    let r = &res[0];
+    assert_eq!(r.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(r.range.start.line, 4);
+    assert_eq!(r.range.start.character, 8);
+    assert_eq!(r.range.end.character, 18);
+    let r = &res[1];
+    assert_eq!(r.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(r.range.start.line, 9);
+    assert_eq!(r.range.start.character, 7);
+    assert_eq!(r.range.end.character, 14);
+    let r = &res[2];
+    assert_eq!(r.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(r.range.start.line, 10);
+    assert_eq!(r.range.start.character, 7);
+    assert_eq!(r.range.end.character, 17);
+    let r = &res[3];
+    assert_eq!(r.uri, ctx.doc_uri("lib/go/Earthfile"));
+    assert_eq!(r.range.start.line, 2);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_references_from_import_alias() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::References>(ReferenceParams {
+            context: ReferenceContext { include_declaration: true },
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 5, character: 12 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 2);
+    assert!(res
+        .iter()
+        .any(|l| l.uri == ctx.doc_uri("lib/go/Earthfile") && l.range.start.line == 6));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_variable_references() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let mut res = ctx
+        .request::<request::References>(ReferenceParams {
+            context: ReferenceContext { include_declaration: true },
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 6, character: 8 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    res.sort_by_key(|l| l.range.start.line);
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0].range.start, Position { line: 6, character: 6 });
+    assert_eq!(res[1].range.start, Position { line: 9, character: 20 });
+    assert_eq!(res[2].range.start, Position { line: 10, character: 6 });
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_global_variable_references() {
+    let mut ctx = TestContext::new("variables");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::References>(ReferenceParams {
+            context: ReferenceContext { include_declaration: false },
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 8, character: 17 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 2);
+    // panic!("Don’t panic!");
+}
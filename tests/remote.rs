@@ -0,0 +1,195 @@
+mod common;
+
+use serde_json::json;
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+async fn initialize_with_checkouts(ctx: &mut TestContext) {
+    ctx.initialize_with(|params| {
+        let root = params.root_uri.as_ref().unwrap().to_file_path().unwrap();
+        let root = root.display();
+        params.initialization_options = Some(json!({
+            "remoteEarthfiles": {
+                "github.com/earthly/lib": format!("{root}/checkouts/lib"),
+                "github.com/acme/tools": format!("{root}/checkouts/tools"),
+            }
+        }));
+    })
+    .await;
+}
+
+async fn goto_definition(ctx: &mut TestContext, line: u32, character: u32) -> Vec<LocationLink> {
+    let res = ctx
+        .request::<request::GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line, character },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    match res {
+        GotoDefinitionResponse::Link(links) => links,
+        // an empty response can't be told apart from an empty array of locations
+        GotoDefinitionResponse::Array(locations) if locations.is_empty() => Vec::new(),
+        _ => panic!("not a link response!"),
+    }
+}
+
+async fn hover(ctx: &mut TestContext, line: u32, character: u32) -> String {
+    let res = ctx
+        .request::<request::HoverRequest>(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                position: Position { line, character },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let HoverContents::Markup(markup) = res.contents else { panic!("not a markup content!") };
+    markup.value
+}
+
+#[tokio::test]
+async fn should_goto_remote_definition() {
+    let mut ctx = TestContext::new("remote");
+    initialize_with_checkouts(&mut ctx).await;
+    let links = goto_definition(&mut ctx, 4, 12).await;
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].target_uri, ctx.doc_uri("checkouts/lib/Earthfile"));
+    assert_eq!(links[0].target_selection_range.start, Position { line: 2, character: 0 });
+
+    // the git reference is ignored
+    let links = goto_definition(&mut ctx, 5, 40).await;
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].target_uri, ctx.doc_uri("checkouts/tools/go/Earthfile"));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_not_resolve_unmapped_remote_references() {
+    let mut ctx = TestContext::new("remote");
+    ctx.initialize().await;
+    assert!(goto_definition(&mut ctx, 4, 12).await.is_empty());
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_hover_remote_references() {
+    let mut ctx = TestContext::new("remote");
+    initialize_with_checkouts(&mut ctx).await;
+    let markup = hover(&mut ctx, 5, 40).await;
+    assert!(markup.contains("Defined in `checkouts/tools/go/Earthfile`"));
+    assert!(markup.contains("The go image"));
+
+    let markup = hover(&mut ctx, 6, 30).await;
+    assert!(
+        markup.starts_with("`+foo` is defined in the remote Earthfile `github.com/unknown/repo`.")
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_ignore_remote_documents() {
+    let mut ctx = TestContext::new("remote");
+    initialize_with_checkouts(&mut ctx).await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(dp.diagnostics.len(), 1);
+
+    // the diagnostics are published again, only for the workspace document
+    ctx.notify::<notification::DidChangeConfiguration>(DidChangeConfigurationParams {
+        settings: json!({ "diagnostics": { "undefined_target": { "severity": "hint" } } }),
+    })
+    .await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(dp.diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: Default::default(),
+            query: "".to_string(),
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    let names: Vec<_> = symbols.into_iter().map(|s| s.name).collect();
+    assert!(names.contains(&"build".to_string()));
+    assert!(!names.contains(&"image".to_string()));
+    assert!(!names.contains(&"HELLO".to_string()));
+    // panic!("Don’t panic!");
+}
+
+fn rename_params(ctx: &TestContext, line: u32, character: u32, new_name: &str) -> RenameParams {
+    RenameParams {
+        text_document_position: TextDocumentPositionParams {
+            position: Position { line, character },
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+        },
+        new_name: new_name.to_string(),
+        work_done_progress_params: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn should_not_rename_in_remote_documents() {
+    let mut ctx = TestContext::new("remote");
+    initialize_with_checkouts(&mut ctx).await;
+    let params = rename_params(&ctx, 3, 2, "build-all");
+    let res = ctx.request::<request::Rename>(params).await.unwrap();
+    let changes = res.changes.unwrap();
+    assert_eq!(changes.keys().collect::<Vec<_>>(), vec![&ctx.doc_uri("Earthfile")]);
+
+    // a remote target can't be renamed
+    let params = rename_params(&ctx, 4, 12, "GREET");
+    let error = ctx.request_error::<request::Rename>(params).await;
+    assert_eq!(error.message, "can't rename HELLO: it is declared in a remote Earthfile");
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_keep_workspace_documents_in_a_checkout() {
+    let mut ctx = TestContext::new("remote");
+    ctx.initialize_with(|params| {
+        let root = params.root_uri.as_ref().unwrap().to_file_path().unwrap();
+        params.initialization_options = Some(json!({
+            "remoteEarthfiles": {
+                "github.com/acme/all": root.display().to_string(),
+                "github.com/earthly/lib": format!("{}/checkouts/lib", root.display()),
+            }
+        }));
+    })
+    .await;
+    // the workspace is also mapped as a checkout, but its documents are not remote ones
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert!(!dp.diagnostics.is_empty());
+
+    let params = rename_params(&ctx, 3, 2, "build-all");
+    let res = ctx.request::<request::Rename>(params).await.unwrap();
+    assert!(res.changes.unwrap().contains_key(&ctx.doc_uri("Earthfile")));
+
+    // a workspace inside the checkout
+    let mut ctx = TestContext::new("remote");
+    let tools = ctx.doc_uri("checkouts/tools");
+    ctx.initialize_with(|params| {
+        let root = params.root_uri.as_ref().unwrap().to_file_path().unwrap();
+        params.initialization_options = Some(json!({
+            "remoteEarthfiles": { "github.com/acme/all": root.display().to_string() }
+        }));
+        params.workspace_folders = Some(vec![WorkspaceFolder { name: "tools".into(), uri: tools }]);
+    })
+    .await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("checkouts/tools/go/Earthfile"));
+    assert_eq!(dp.diagnostics[0].message, "undefined target +missing");
+    // panic!("Don’t panic!");
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+#[tokio::test]
+async fn should_prepare_rename() {
+    let mut ctx = TestContext::new("simple");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::PrepareRenameRequest>(TextDocumentPositionParams {
+            position: Position { line: 3, character: 9 },
+            text_document: TextDocumentIdentifier { uri: ctx.doc_uri("bar/Earthfile") },
+        })
+        .await
+        .unwrap();
+    let PrepareRenameResponse::RangeWithPlaceholder { range, placeholder } = res else {
+        panic!("not a range with placeholder!");
+    };
+    assert_eq!(placeholder, "rust");
+    assert_eq!(
+        range,
+        Range {
+            start: Position { line: 3, character: 11 },
+            end: Position { line: 3, character: 15 }
+        }
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_rename_target() {
+    let mut ctx = TestContext::new("simple");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::Rename>(RenameParams {
+            new_name: "rust-base".to_string(),
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 2, character: 2 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let changes = res.changes.unwrap();
+    assert_eq!(changes.len(), 3);
+    let mut edits = changes[&ctx.doc_uri("Earthfile")].clone();
+    edits.sort_by_key(|e| e.range.start.line);
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[0].new_text, "rust-base");
+    assert_eq!(
+        edits[0].range,
+        Range {
+            start: Position { line: 2, character: 0 },
+            end: Position { line: 2, character: 4 }
+        }
+    );
+    assert_eq!(
+        edits[1].range,
+        Range {
+            start: Position { line: 6, character: 8 },
+            end: Position { line: 6, character: 12 }
+        }
+    );
+    let edits = &changes[&ctx.doc_uri("bar/Earthfile")];
+    assert_eq!(edits.len(), 1);
+    assert_eq!(
+        edits[0].range,
+        Range {
+            start: Position { line: 3, character: 11 },
+            end: Position { line: 3, character: 15 }
+        }
+    );
+    assert_eq!(changes[&ctx.doc_uri("foo/Earthfile")].len(), 1);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_rename_target_with_import_alias() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::Rename>(RenameParams {
+            new_name: "check".to_string(),
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 4, character: 16 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let changes = res.changes.unwrap();
+    assert_eq!(changes[&ctx.doc_uri("Earthfile")].len(), 3);
+    assert_eq!(changes[&ctx.doc_uri("lib/go/Earthfile")].len(), 1);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_not_rename_target_with_wildcard_reference() {
+    let mut ctx = TestContext::new("simple");
+    ctx.initialize().await;
+    // ./*/+docker also references the docker target of bar/Earthfile
+    let err = ctx
+        .request_error::<request::Rename>(RenameParams {
+            new_name: "image".to_string(),
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 2, character: 2 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("foo/Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await;
+    assert_eq!(
+        err.message,
+        "can't rename docker: the wildcard reference ./*/+docker in Earthfile also matches other Earthfiles"
+    );
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_not_rename_function_to_target_name() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let err = ctx
+        .request_error::<request::Rename>(RenameParams {
+            new_name: "func".to_string(),
+            text_document_position: TextDocumentPositionParams {
+                position: Position { line: 5, character: 12 },
+                text_document: TextDocumentIdentifier { uri: ctx.doc_uri("Earthfile") },
+            },
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await;
+    assert_eq!(err.message, "invalid function name: func");
+    // panic!("Don’t panic!");
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use earthlyls::diagnostic::QuickFix;
+use serde_json::json;
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+async fn workspace_symbol_names(ctx: &mut TestContext) -> Vec<String> {
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: Default::default(),
+            query: "".to_string(),
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    symbols.into_iter().map(|s| s.name).collect()
+}
+
+#[tokio::test]
+async fn should_apply_initialization_options() {
+    let mut ctx = TestContext::new("settings");
+    ctx.initialize_with(|params| {
+        params.initialization_options = Some(json!({
+            "earthlyVersion": "0.7",
+            "diagnostics": { "undefined_target": { "severity": "warning" } },
+            "include": ["**/*.earth"],
+            "exclude": ["ignored/**"],
+        }));
+    })
+    .await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    let ds = dp.diagnostics;
+    assert_eq!(ds.len(), 2);
+    assert_eq!(ds[0].message, "no version specified");
+    assert_eq!(QuickFix::from_diagnostic(&ds[0]).unwrap().title, "Add VERSION 0.7");
+    assert_eq!(ds[1].message, "undefined target +missing");
+    assert_eq!(ds[1].severity, Some(DiagnosticSeverity::WARNING));
+
+    let names = workspace_symbol_names(&mut ctx).await;
+    assert!(names.contains(&"common".to_string()));
+    assert!(!names.contains(&"ignored".to_string()));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_apply_configuration_changes() {
+    let mut ctx = TestContext::new("settings");
+    ctx.initialize().await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.diagnostics.len(), 2);
+
+    ctx.notify::<notification::DidChangeConfiguration>(DidChangeConfigurationParams {
+        settings: json!({
+            "earthlyls": {
+                "diagnostics": {
+                    "missing_version": { "enabled": false },
+                    "undefined_target": { "severity": "hint" },
+                },
+            },
+        }),
+    })
+    .await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(dp.diagnostics.len(), 1);
+    assert_eq!(dp.diagnostics[0].message, "undefined target +missing");
+    assert_eq!(dp.diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+
+    ctx.notify::<notification::DidChangeConfiguration>(DidChangeConfigurationParams {
+        settings: json!({ "exclude": ["ignored/**"] }),
+    })
+    .await;
+    assert!(!workspace_symbol_names(&mut ctx).await.contains(&"ignored".to_string()));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_apply_project_settings() {
+    let mut ctx = TestContext::new("project_config");
+    ctx.initialize().await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    let ds = dp.diagnostics;
+    assert_eq!(ds.len(), 2);
+    assert_eq!(ds[0].message, "undefined target +other");
+    assert_eq!(ds[1].message, "undeclared variable FOO");
+    assert_eq!(ds[1].severity, Some(DiagnosticSeverity::ERROR));
+
+    assert!(!workspace_symbol_names(&mut ctx).await.contains(&"vendored".to_string()));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_override_project_settings() {
+    let mut ctx = TestContext::new("project_config");
+    ctx.initialize_with(|params| {
+        params.initialization_options = Some(json!({
+            "diagnostics": { "missing_version": { "enabled": true } },
+        }));
+    })
+    .await;
+    let ds = ctx.recv::<PublishDiagnosticsParams>().await.diagnostics;
+    assert_eq!(ds.len(), 3);
+    assert_eq!(ds[0].message, "no version specified");
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_load_documents_with_invalid_project_settings() {
+    let mut ctx = TestContext::new("bad_config");
+    ctx.initialize().await;
+    // the documents are loaded, and the rules keep their default settings
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(dp.diagnostics.len(), 1);
+    assert_eq!(dp.diagnostics[0].message, "undefined target +missing");
+    assert!(workspace_symbol_names(&mut ctx).await.contains(&"image".to_string()));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_keep_settings_on_empty_configuration_changes() {
+    let mut ctx = TestContext::new("settings");
+    ctx.initialize_with(|params| {
+        params.initialization_options = Some(json!({
+            "earthlyVersion": "0.7",
+            "diagnostics": { "undefined_target": { "severity": "warning" } },
+            "include": ["**/*.earth"],
+            "exclude": ["ignored/**"],
+        }));
+    })
+    .await;
+    assert_eq!(ctx.recv::<PublishDiagnosticsParams>().await.diagnostics.len(), 2);
+
+    // vscode sends null when nothing is configured, and neovim an empty object
+    for settings in [json!(null), json!({})] {
+        ctx.notify::<notification::DidChangeConfiguration>(DidChangeConfigurationParams {
+            settings,
+        })
+        .await;
+    }
+    let names = workspace_symbol_names(&mut ctx).await;
+    assert!(names.contains(&"common".to_string()));
+    assert!(!names.contains(&"ignored".to_string()));
+
+    ctx.notify::<notification::DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: ctx.doc_uri("Earthfile"),
+            language_id: "earthfile".to_string(),
+            version: 1,
+            text: "build:\n    BUILD +missing\n    BUILD +other\n".to_string(),
+        },
+    })
+    .await;
+    let ds = ctx.recv::<PublishDiagnosticsParams>().await.diagnostics;
+    assert_eq!(ds.len(), 3);
+    assert_eq!(QuickFix::from_diagnostic(&ds[0]).unwrap().title, "Add VERSION 0.7");
+    assert_eq!(ds[1].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(ds[2].severity, Some(DiagnosticSeverity::WARNING));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_clear_the_diagnostics_of_the_excluded_documents() {
+    let mut ctx = TestContext::new("settings");
+    std::fs::write(
+        ctx.doc_uri("ignored/Earthfile").to_file_path().unwrap(),
+        "ignored:\n    BUILD +missing\n",
+    )
+    .unwrap();
+    ctx.initialize().await;
+    assert_eq!(ctx.recv::<PublishDiagnosticsParams>().await.uri, ctx.doc_uri("Earthfile"));
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("ignored/Earthfile"));
+    assert_eq!(dp.diagnostics.len(), 2);
+
+    ctx.notify::<notification::DidChangeConfiguration>(DidChangeConfigurationParams {
+        settings: json!({ "exclude": ["ignored/**"] }),
+    })
+    .await;
+    assert_eq!(ctx.recv::<PublishDiagnosticsParams>().await.uri, ctx.doc_uri("Earthfile"));
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("ignored/Earthfile"));
+    assert!(dp.diagnostics.is_empty());
+    // panic!("Don’t panic!");
+}
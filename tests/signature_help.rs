@@ -0,0 +1,80 @@
+mod common;
+
+use tower_lsp::lsp_types::*;
+
+use crate::common::*;
+
+async fn signature_help(ctx: &mut TestContext, line: u32, character: u32) -> Option<SignatureHelp> {
+    let uri = ctx.doc_uri("Earthfile");
+    ctx.request::<request::SignatureHelpRequest>(SignatureHelpParams {
+        text_document_position_params: TextDocumentPositionParams {
+            position: Position { line, character },
+            text_document: TextDocumentIdentifier { uri },
+        },
+        work_done_progress_params: Default::default(),
+        context: None,
+    })
+    .await
+}
+
+#[tokio::test]
+async fn should_show_the_target_args() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let res = signature_help(&mut ctx, 21, 41).await.unwrap();
+    let signature = &res.signatures[0];
+    assert_eq!(
+        signature.label,
+        "./services/api+image --REGISTRY=docker.io --TAG --PLATFORM=linux/amd64"
+    );
+    assert_eq!(res.active_parameter, None);
+    let params = signature.parameters.as_ref().unwrap();
+    assert_eq!(params.len(), 3);
+    assert_eq!(params[1].label, ParameterLabel::LabelOffsets([42, 47]));
+    assert_eq!(params[1].documentation, Some(Documentation::String("required".to_string())));
+
+    // in the middle of a build arg
+    let res = signature_help(&mut ctx, 21, 36).await.unwrap();
+    assert_eq!(res.active_parameter, Some(1));
+
+    // in a partial build arg name
+    let res = signature_help(&mut ctx, 21, 34).await.unwrap();
+    assert_eq!(res.active_parameter, Some(1));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_show_the_function_args() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let res = signature_help(&mut ctx, 22, 23).await.unwrap();
+    assert_eq!(res.signatures[0].label, "mylib+LIB_FUNC --MESSAGE");
+
+    // not a call
+    assert_eq!(signature_help(&mut ctx, 9, 11).await, None);
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_count_the_offsets_in_utf16() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    ctx.notify::<notification::DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: ctx.doc_uri("Earthfile"),
+            language_id: "earthfile".to_string(),
+            version: 1,
+            text:
+                "VERSION 0.8\ngreet:\n    ARG EMOJI=🚀\n    ARG NAME\nbuild:\n    BUILD +greet --\n"
+                    .to_string(),
+        },
+    })
+    .await;
+    let res = signature_help(&mut ctx, 5, 19).await.unwrap();
+    let signature = &res.signatures[0];
+    assert_eq!(signature.label, "+greet --EMOJI=🚀 --NAME");
+    let params = signature.parameters.as_ref().unwrap();
+    assert_eq!(params[0].label, ParameterLabel::LabelOffsets([7, 17]));
+    assert_eq!(params[1].label, ParameterLabel::LabelOffsets([18, 24]));
+    // panic!("Don’t panic!");
+}
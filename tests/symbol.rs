@@ -41,3 +41,59 @@ async fn should_provide_workspace_symbols_with_query() {
     assert_eq!(symbols.len(), 3);
     // panic!("Don’t panic!");
 }
+
+#[tokio::test]
+async fn should_provide_import_alias_symbols() {
+    let mut ctx = TestContext::new("import");
+    ctx.initialize().await;
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            query: "go".to_string(),
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    assert_eq!(symbols.len(), 2);
+    assert!(symbols.iter().all(|s| s.kind == SymbolKind::MODULE));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_provide_ranked_workspace_symbols() {
+    let mut ctx = TestContext::new("completion");
+    ctx.initialize().await;
+    let res = workspace_symbols(&mut ctx, "api+img").await;
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].name, "image");
+    assert_eq!(res[0].container_name, Some("services/api".to_string()));
+
+    let res = workspace_symbols(&mut ctx, "PLAT").await;
+    assert_eq!(res[0].name, "PLATFORM");
+    assert_eq!(res[0].container_name, Some("services/api+image".to_string()));
+
+    // the exact matches first
+    let res = workspace_symbols(&mut ctx, "build").await;
+    assert!(res.len() > 1);
+    assert!(res.iter().take_while(|s| s.name == "build").count() > 1);
+    assert!(res.iter().skip_while(|s| s.name == "build").all(|s| s.name != "build"));
+    // panic!("Don’t panic!");
+}
+
+async fn workspace_symbols(ctx: &mut TestContext, query: &str) -> Vec<SymbolInformation> {
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: PartialResultParams { partial_result_token: None },
+            query: query.to_string(),
+            work_done_progress_params: WorkDoneProgressParams { work_done_token: None },
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    symbols
+}
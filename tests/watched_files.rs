@@ -116,3 +116,139 @@ async fn should_react_to_deleted_notification() {
     assert_eq!(res.len(), 2);
     // panic!("Don’t panic!");
 }
+
+#[tokio::test]
+async fn should_reload_the_project_settings() {
+    let mut ctx = TestContext::new("project_config");
+    ctx.initialize().await;
+    let ds = ctx.recv::<PublishDiagnosticsParams>().await.diagnostics;
+    assert_eq!(ds.len(), 2);
+
+    // enable the missing_version rule again, and stop excluding the vendor directory
+    std::fs::write(
+        ctx.doc_uri(".earthlyls.toml").to_file_path().unwrap(),
+        "[diagnostics]\nundeclared-variable = { severity = \"error\" }\n",
+    )
+    .unwrap();
+    ctx.notify::<notification::DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: ctx.doc_uri(".earthlyls.toml"),
+            typ: FileChangeType::CHANGED,
+        }],
+    })
+    .await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert_eq!(dp.diagnostics.len(), 3);
+    assert_eq!(dp.diagnostics[0].message, "no version specified");
+
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: Default::default(),
+            query: "vendored".to_string(),
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    assert_eq!(symbols.len(), 1);
+    // the configuration file is not loaded as an Earthfile
+    assert_eq!(symbols[0].location.uri, ctx.doc_uri("vendor/Earthfile"));
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_ignore_the_excluded_files() {
+    let mut ctx = TestContext::new("settings");
+    ctx.initialize_with(|params| {
+        params.initialization_options = Some(serde_json::json!({ "exclude": ["ignored/**"] }));
+    })
+    .await;
+
+    std::fs::write(
+        ctx.doc_uri("ignored/Earthfile").to_file_path().unwrap(),
+        "VERSION 0.8\n\nstill-ignored:\n    FROM alpine\n",
+    )
+    .unwrap();
+    ctx.notify::<notification::DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: ctx.doc_uri("ignored/Earthfile"),
+            typ: FileChangeType::CHANGED,
+        }],
+    })
+    .await;
+
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: Default::default(),
+            query: "ignored".to_string(),
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    assert!(symbols.is_empty());
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_ignore_the_files_excluded_by_the_project() {
+    let mut ctx = TestContext::new("project_config");
+    ctx.initialize().await;
+
+    std::fs::create_dir(ctx.doc_uri("vendor/new").to_file_path().unwrap()).unwrap();
+    std::fs::write(
+        ctx.doc_uri("vendor/new/Earthfile").to_file_path().unwrap(),
+        "VERSION 0.8\n\nnew-vendored:\n    FROM alpine\n",
+    )
+    .unwrap();
+    ctx.notify::<notification::DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: ctx.doc_uri("vendor/new/Earthfile"),
+            typ: FileChangeType::CREATED,
+        }],
+    })
+    .await;
+
+    let res = ctx
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            partial_result_params: Default::default(),
+            query: "vendored".to_string(),
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let WorkspaceSymbolResponse::Flat(symbols) = res else {
+        panic!("not a flat response!");
+    };
+    assert!(symbols.is_empty());
+    // panic!("Don’t panic!");
+}
+
+#[tokio::test]
+async fn should_clear_the_diagnostics_of_the_documents_excluded_by_the_project() {
+    let mut ctx = TestContext::new("project_config");
+    ctx.initialize().await;
+    assert_eq!(ctx.recv::<PublishDiagnosticsParams>().await.diagnostics.len(), 2);
+
+    std::fs::write(
+        ctx.doc_uri(".earthlyls.toml").to_file_path().unwrap(),
+        "exclude = [\"Earthfile\"]\n",
+    )
+    .unwrap();
+    ctx.notify::<notification::DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: ctx.doc_uri(".earthlyls.toml"),
+            typ: FileChangeType::CHANGED,
+        }],
+    })
+    .await;
+    let dp = ctx.recv::<PublishDiagnosticsParams>().await;
+    assert_eq!(dp.uri, ctx.doc_uri("Earthfile"));
+    assert!(dp.diagnostics.is_empty());
+    // panic!("Don’t panic!");
+}